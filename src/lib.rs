@@ -1,290 +1,4864 @@
 use core::fmt;
 use lazy_static::lazy_static;
-use regex::Regex;
-use std::{collections::HashMap, env::current_dir, hash::Hash, path::Path, path::PathBuf};
+use regex::{Captures, Regex};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
+    env::current_dir,
+    hash::Hash,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 /// A compiler warning
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Warning {
-    /// Name of the warning, minus the initial "-W"
+    /// Name of the warning, minus the prefix of its flag, such as "-W"
     name: String,
 
     /// File the warning appears in
     file: PathBuf,
 
+    /// Line number the warning appears on, if known
+    line: Option<usize>,
+
+    /// Column number the warning appears at, if known
+    column: Option<usize>,
+
     /// Set of potentially interesting keywords from line that raised warning
     keywords: Vec<String>,
+
+    /// Compiler or tool that most likely produced the warning
+    toolchain: String,
+
+    /// The text the warning was parsed from, if it was kept
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    raw: Option<String>,
+
+    /// The message after `warning:`, without the name, if it was kept
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    message: Option<String>,
+}
+
+impl Warning {
+    /// Order warnings by file, then line, column, and name, so that sorting
+    /// groups them by where they are. Warnings without a line come first in
+    /// their file. Keywords and everything else are ignored, so this can be
+    /// `Equal` for warnings that aren't `==`
+    pub fn location_cmp(&self, other: &Warning) -> std::cmp::Ordering {
+        (&self.file, self.line, self.column, &self.name).cmp(&(
+            &other.file,
+            other.line,
+            other.column,
+            &other.name,
+        ))
+    }
+
+    /// Whether the warning says which file it's in, which linker warnings
+    /// don't. Those without are left out of the counts of files and
+    /// directories
+    fn has_file(&self) -> bool {
+        !self.file.as_os_str().is_empty()
+    }
+
+    /// The name, and the message with anything specific to this warning
+    /// replaced, if the message was kept, so the same problem in different
+    /// places has the same signature
+    fn signature(&self) -> String {
+        match &self.message {
+            Some(message) => format!("{}: {}", self.name, message_template(message)),
+            None => self.name.clone(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WarningCollection {
     /// Set of warnings from a whole project
     warnings: Vec<Warning>,
 
     /// Mapping of warning names to counts
-    names: HashMap<String, i16>,
+    names: HashMap<String, i32>,
 
     /// Mapping of filenames to counts
-    files: HashMap<PathBuf, i16>,
+    files: HashMap<PathBuf, i32>,
 
     /// Mapping of directory names to counts
-    directories: HashMap<PathBuf, i16>,
+    directories: HashMap<PathBuf, i32>,
+
+    /// Mapping of filenames, ignoring their directories, to counts
+    #[cfg_attr(feature = "serde", serde(default))]
+    basenames: HashMap<PathBuf, i32>,
 
     /// Mapping of keywords to counts
-    keywords: HashMap<String, i16>,
+    keywords: HashMap<String, i32>,
+
+    /// Whether keywords were lowercased when counted
+    #[cfg_attr(feature = "serde", serde(default))]
+    fold_keyword_case: bool,
+
+    /// Whether parsing stopped early because of the limits in
+    /// [`ParseOptions`]
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    truncated: bool,
+
+    /// Only every Nth warning was kept, with counts scaled up to match
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    sample_every: Option<usize>,
+
+    /// Time spent parsing and counting, with [`ParseOptions::profile`]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    profile: Option<Profile>,
 }
 
-fn make_keywords<T: AsRef<str>>(
-    text: &str,
-    keyword_len: usize,
-    ignored_keywords: &[T],
-) -> Vec<String> {
-    lazy_static! {
-        static ref WORDS_RE: Regex = Regex::new(r"\b[a-zA-Z_]\w+\b").unwrap();
+/// Errors from building a [`WarningCollection`]
+#[derive(Debug)]
+pub enum Error {
+    /// A custom parser regex failed to compile
+    Regex(regex::Error),
+
+    /// A custom parser regex is missing a required named group
+    MissingGroup(&'static str),
+
+    /// A warning budget isn't of the form `N` or `NAME=N`, with a name and
+    /// N at least 0
+    InvalidBudget(String),
+
+    /// Not the name of a [`Category`]
+    InvalidCategory(String),
+
+    /// A line in a mapping file isn't of the form `KEY=VALUE`
+    InvalidMapping { line: usize, text: String },
+
+    /// A key in a mapping file is given more than once
+    DuplicateMapping {
+        line: usize,
+        first: usize,
+        key: String,
+    },
+
+    /// A range of [`Buckets`] isn't of the form `N`, `N-M`, or `N+`
+    InvalidBucket(String),
+
+    /// A [`LineRange`] isn't of the form `FILE:START-END`
+    InvalidLineRange(String),
+
+    /// A [`LineTemplate`] has a token that isn't one of `{count}`, `{key}`,
+    /// `{percent}`, `{rank}`, or `{bar}`, or a `{` without a `}`
+    InvalidToken(String),
+
+    /// Malformed cppcheck XML
+    Xml(quick_xml::Error),
+
+    /// Something that needs the individual warnings was asked of a
+    /// collection with only counts
+    CountsOnly(&'static str),
+
+    /// A log file couldn't be read
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Regex(err) => write!(f, "invalid regex: {err}"),
+            Error::MissingGroup(group) => {
+                write!(f, "regex is missing required named group `{group}`")
+            }
+            Error::InvalidBudget(budget) => {
+                write!(
+                    f,
+                    "invalid budget `{budget}`, expected `N` or `NAME=N`, with N at least 0"
+                )
+            }
+            Error::Io { path, source } => {
+                write!(f, "could not read file `{}`: {source}", path.display())
+            }
+            Error::InvalidCategory(category) => {
+                let known = Category::ALL.map(|category| category.name()).join(", ");
+                write!(f, "unknown category `{category}`, expected one of {known}")
+            }
+            Error::InvalidMapping { line, text } => {
+                write!(f, "line {line}: expected `KEY=VALUE`, found `{text}`")
+            }
+            Error::DuplicateMapping { line, first, key } => {
+                write!(f, "line {line}: `{key}` was already given on line {first}")
+            }
+            Error::InvalidBucket(bucket) => {
+                write!(f, "invalid bucket `{bucket}`, expected `N`, `N-M`, or `N+`")
+            }
+            Error::InvalidLineRange(range) => {
+                write!(f, "invalid line range `{range}`, expected `FILE:START-END`")
+            }
+            Error::InvalidToken(token) => write!(
+                f,
+                "unknown token `{token}` in line template, expected one of \
+                 {{count}}, {{key}}, {{percent}}, {{rank}}, or {{bar}}"
+            ),
+            Error::Xml(err) => write!(f, "invalid XML: {err}"),
+            Error::CountsOnly(action) => write!(
+                f,
+                "can't {action} with only the counts of warnings, it needs the warnings themselves"
+            ),
+        }
     }
+}
 
-    WORDS_RE
-        .find_iter(text)
-        .filter(|mat| mat.as_str().len() >= keyword_len)
-        .map(|mat| mat.as_str())
-        .filter(|&word| {
-            !ignored_keywords
-                .iter()
-                .any(|ignore| ignore.as_ref() == word)
-        })
-        .map(|word| word.to_string())
-        .collect()
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Regex(err) => Some(err),
+            Error::Io { source, .. } => Some(source),
+            Error::Xml(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
-fn count_warning_fn<F, T>(warnings: &[Warning], f: F) -> HashMap<T, i16>
-where
-    F: Fn(&Warning) -> T,
-    T: Eq + std::hash::Hash,
-{
-    let mut result = HashMap::new();
+impl From<quick_xml::Error> for Error {
+    fn from(err: quick_xml::Error) -> Self {
+        Error::Xml(err)
+    }
+}
 
-    for warning in warnings {
-        let count = result.entry(f(warning)).or_insert(0);
-        *count += 1;
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Error::Regex(err)
     }
+}
 
-    return result;
+/// A user-supplied regex for warnings in a bespoke format.
+///
+/// Must contain the named groups `file` and `name`, and may optionally
+/// contain `line`, `col`, and `text` (used for keywords). The prefix of the
+/// flag, `-W` by default, is stripped from `name` where it has one
+#[derive(Debug, Clone)]
+pub struct CustomParser {
+    regex: Regex,
+    flag_prefix: String,
 }
 
-fn count_warning_types(warnings: &[Warning]) -> HashMap<String, i16> {
-    count_warning_fn(warnings, |warning| warning.name.clone())
+impl CustomParser {
+    const REQUIRED_GROUPS: [&'static str; 2] = ["file", "name"];
+    const DEFAULT_FLAG_PREFIX: &'static str = "-W";
+
+    pub fn new(pattern: &str) -> Result<CustomParser, Error> {
+        let regex = Regex::new(pattern)?;
+
+        for group in CustomParser::REQUIRED_GROUPS {
+            if !regex.capture_names().any(|name| name == Some(group)) {
+                return Err(Error::MissingGroup(group));
+            }
+        }
+
+        Ok(CustomParser {
+            regex,
+            flag_prefix: CustomParser::DEFAULT_FLAG_PREFIX.to_string(),
+        })
+    }
+
+    /// Strip `prefix` from warning names instead, or nothing if it's empty
+    pub fn with_flag_prefix(self, prefix: &str) -> CustomParser {
+        CustomParser {
+            flag_prefix: prefix.to_string(),
+            ..self
+        }
+    }
 }
 
-fn count_warning_files(warnings: &[Warning]) -> HashMap<PathBuf, i16> {
-    count_warning_fn(warnings, |warning| warning.file.clone())
+impl std::str::FromStr for CustomParser {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        CustomParser::new(pattern)
+    }
 }
 
-fn count_warning_directories(warnings: &[Warning]) -> HashMap<PathBuf, i16> {
-    count_warning_fn(warnings, |warning| {
-        warning.file.parent().unwrap_or(&warning.file).to_path_buf()
-    })
+/// Which text of a warning its keywords are found in
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum KeywordSource {
+    /// The source line the warning is about, or the message if there isn't
+    /// one
+    #[default]
+    Code,
+
+    /// The message after `warning:`
+    Message,
+
+    /// Both, leaving out keywords from the message already in the code
+    Both,
 }
 
-fn count_warning_keywords(warnings: &[Warning]) -> HashMap<String, i16> {
-    let keywords = warnings
-        .iter()
-        .map(|warning| &warning.keywords)
-        .flatten()
-        .collect::<Vec<&String>>();
+/// Options controlling how warnings are parsed from a log
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Minimum length of interesting keywords
+    pub keyword_len: usize,
 
-    let mut result = HashMap::new();
-    for keyword in keywords {
-        *result.entry(keyword.clone()).or_default() += 1;
+    /// Which text of each warning to find keywords in
+    pub keyword_source: KeywordSource,
+
+    /// Keywords to ignore from warnings
+    pub ignored_keywords: Vec<String>,
+
+    /// Names of warnings to drop entirely, which may contain `*` wildcards
+    pub ignored_names: Vec<String>,
+
+    /// Lines of files to drop any warnings in, such as generated code
+    pub excluded_ranges: Vec<LineRange>,
+
+    /// Names to count warnings under instead of their own
+    pub aliases: Aliases,
+
+    /// Extra parsers to run after the built-in ones
+    pub custom_parsers: Vec<CustomParser>,
+
+    /// Lowercase keywords when counting them, merging case variants
+    pub fold_keyword_case: bool,
+
+    /// Resolve file paths against the filesystem where they exist, so that
+    /// different references to the same file are counted together
+    pub canonicalize_existing: bool,
+
+    /// Allow keywords to start with a digit, such as `0xDEADBEEF`
+    pub keyword_include_numeric: bool,
+
+    /// Only keep the first occurrence of each keyword in a warning
+    pub unique_keywords_per_warning: bool,
+
+    /// Keep the text each warning was parsed from, for `explain`
+    pub keep_raw: bool,
+
+    /// Keep the message of each warning, for [`Category::Signatures`]
+    pub keep_messages: bool,
+
+    /// Only take keywords from the part of the source line underlined by
+    /// the compiler, where there is one
+    pub keyword_underlined_only: bool,
+
+    /// Number of columns between tab stops in source lines
+    pub tab_width: usize,
+
+    /// Also count `#pragma message` notes, named after their messages
+    pub include_pragmas: bool,
+
+    /// Also count warnings from linkers, such as `ld: warning: ...`, named
+    /// `linker` and counted by message in [`Category::Linker`]. They have
+    /// no file or keywords
+    pub include_linker: bool,
+
+    /// Number of words from the message to name warnings without a flag
+    /// after, such as Swift's
+    pub message_name_words: usize,
+
+    /// Add the keywords from `note:` lines to the warning before them
+    pub include_notes_keywords: bool,
+
+    /// Replace C++ template arguments with `<...>` before finding keywords
+    pub collapse_templates: bool,
+
+    /// Join warnings that were hard-wrapped onto several lines, such as by
+    /// a terminal, back onto one line before parsing
+    pub unwrap_lines: bool,
+
+    /// Stop parsing at the first warning in a new file once there are
+    /// warnings in this many files
+    pub max_files: Option<usize>,
+
+    /// Stop parsing once this many warnings have been found, keeping the
+    /// first ones in the log whichever parser found them
+    pub max_warnings: Option<usize>,
+
+    /// Only keep every Nth warning in the log, skipping the rest before
+    /// finding their keywords, and scaling the counts back up by N, for a
+    /// quick estimate from a very large log
+    pub sample_every: Option<usize>,
+
+    /// Time each stage of parsing, for [`WarningCollection::profile`].
+    /// Nothing is timed without it
+    pub profile: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            keyword_len: 5,
+            keyword_source: KeywordSource::Code,
+            ignored_keywords: Vec::new(),
+            ignored_names: Vec::new(),
+            excluded_ranges: Vec::new(),
+            aliases: Aliases::default(),
+            custom_parsers: Vec::new(),
+            fold_keyword_case: false,
+            canonicalize_existing: false,
+            keyword_include_numeric: false,
+            unique_keywords_per_warning: false,
+            keep_raw: false,
+            keep_messages: false,
+            keyword_underlined_only: false,
+            tab_width: 8,
+            include_pragmas: false,
+            include_linker: false,
+            message_name_words: 3,
+            include_notes_keywords: false,
+            collapse_templates: false,
+            unwrap_lines: false,
+            max_files: None,
+            max_warnings: None,
+            sample_every: None,
+            profile: false,
+        }
     }
+}
 
+/// Replace the arguments of C++ templates, including any nested templates,
+/// with `<...>`. Unbalanced brackets, such as comparisons, are left alone
+fn collapse_templates(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(text.len());
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == '<' {
+            let mut depth = 0;
+            let close = chars[index..].iter().position(|&c| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            if let Some(close) = close {
+                result.push_str("<...>");
+                index += close + 1;
+                continue;
+            }
+        }
+        result.push(chars[index]);
+        index += 1;
+    }
     result
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct CountDiff(i16);
+fn make_keywords(text: &str, options: &ParseOptions) -> Vec<String> {
+    lazy_static! {
+        static ref WORDS_RE: Regex = Regex::new(r"\b[a-zA-Z_]\w+\b").unwrap();
+        // Tokens can start with a digit, but must contain at least one letter
+        // so that source line numbers aren't picked up
+        static ref NUMERIC_WORDS_RE: Regex = Regex::new(r"\b\w*[a-zA-Z_]\w*\b").unwrap();
+    }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct WarningCollectionDiff {
-    /// Mapping of warning names to counts
-    names: HashMap<String, i16>,
+    let words_re: &Regex = if options.keyword_include_numeric {
+        &NUMERIC_WORDS_RE
+    } else {
+        &WORDS_RE
+    };
 
-    /// Mapping of filenames to counts
-    files: HashMap<PathBuf, i16>,
+    let text = if options.collapse_templates {
+        Cow::Owned(collapse_templates(text))
+    } else {
+        Cow::Borrowed(text)
+    };
 
-    /// Mapping of directory names to counts
-    directories: HashMap<PathBuf, i16>,
+    let mut seen = HashSet::new();
+    words_re
+        .find_iter(&text)
+        .filter(|mat| mat.as_str().len() >= options.keyword_len)
+        .map(|mat| mat.as_str())
+        .filter(|&word| !options.ignored_keywords.iter().any(|ignore| ignore == word))
+        .filter(|&word| !options.unique_keywords_per_warning || seen.insert(word))
+        .map(|word| word.to_string())
+        .collect()
+}
 
-    /// Mapping of keywords to counts
-    keywords: HashMap<String, i16>,
+/// Replace tabs in the code of a numbered source line with spaces, up to
+/// the next multiple of `tab_width` columns, as gcc does when placing its
+/// underline
+fn expand_tabs(source: &str, tab_width: usize) -> String {
+    let code_start = source.find("| ").map_or(0, |index| index + 2);
+    let (gutter, code) = source.split_at(code_start);
+
+    let mut expanded = gutter.to_string();
+    let mut column = 0;
+    for c in code.chars() {
+        if c == '\t' && tab_width > 0 {
+            let spaces = tab_width - column % tab_width;
+            expanded.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
 }
 
-impl WarningCollection {
-    pub fn new<T: AsRef<str>>(
-        content: &str,
-        keyword_len: usize,
-        ignored_keywords: &[T],
-    ) -> WarningCollection {
-        lazy_static! {
-            static ref WARN_RE: Regex = Regex::new(
-                r"(?x)
-            (?P<file>.*):\d+:\d+:\s*                 # Filename
-            (?P<text_before>\n\n\s+\d+\ \|.*\n.*\n)? # Possible source code (gfortran)
-            [wW]arning:.*\[(-W)?(?P<name>.*)\]       # Warning name
-            (?P<text_after>\n\s+\d+\ \|.*)?          # Possible source code (gcc/clang)
-            "
-            )
-            .unwrap();
+/// The words in `source` that are underlined with `^~~~` in `underline`,
+/// assuming the two lines are aligned. gfortran instead marks positions
+/// with numbers, such as `1`, in which case the word at the first one is
+/// used
+fn underlined_text<'a>(source: &'a str, underline: &str) -> Option<&'a str> {
+    let is_marker = |c: char| c == '^' || c == '~';
+    let (start, end) = match underline.find(is_marker) {
+        Some(start) => (start, underline.rfind(is_marker)? + 1),
+        None => {
+            // Skip the gutter, in case it ever has line numbers too
+            let gutter = underline.find('|').map_or(0, |index| index + 1);
+            let start = underline
+                .get(gutter..)?
+                .find(|c: char| c.is_ascii_digit())?
+                + gutter;
+            (start, start + 1)
         }
+    };
 
-        let cwd = current_dir().unwrap_or(PathBuf::from(""));
+    // Extend the span to whole words, in case the underline is off by a bit
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = source
+        .get(..start)?
+        .rfind(|c| !is_word(c))
+        .map_or(0, |index| index + 1);
+    let end = source
+        .get(end..)
+        .and_then(|rest| rest.find(|c| !is_word(c)))
+        .map_or(source.len(), |index| index + end);
+    source.get(start..end)
+}
 
-        let result = WARN_RE
-            .captures_iter(content)
-            .map(|cap| Warning {
-                name: String::from(&cap["name"]),
-                file: {
-                    let filename = PathBuf::from(&cap["file"]);
-                    filename
-                        .strip_prefix(&cwd)
-                        .unwrap_or(&filename)
-                        .to_path_buf()
-                },
-                keywords: match cap.name("text_after") {
-                    Some(capture) => make_keywords(capture.as_str(), keyword_len, ignored_keywords),
-                    _ => match cap.name("text_before") {
-                        Some(capture) => {
-                            make_keywords(capture.as_str(), keyword_len, ignored_keywords)
-                        }
-                        _ => Vec::new(),
-                    },
-                },
-            })
-            .collect::<Vec<_>>();
+/// Path to a warning's file as it will be counted: relative to `cwd` if
+/// possible, and optionally resolved against the filesystem
+fn resolve_file(file: &str, cwd: &Path, options: &ParseOptions) -> PathBuf {
+    let mut filename = PathBuf::from(file);
+    if options.canonicalize_existing {
+        filename = std::fs::canonicalize(&filename)
+            .unwrap_or_else(|_| normalize_lexically(&cwd.join(&filename)));
+    }
+    filename
+        .strip_prefix(cwd)
+        .unwrap_or(&filename)
+        .to_path_buf()
+}
 
-        let names = count_warning_types(&result);
-        let files = count_warning_files(&result);
-        let directories = count_warning_directories(&result);
-        let keywords = count_warning_keywords(&result);
+/// Remove `.` components and resolve `..` components from a path, without
+/// touching the filesystem
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(result.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
 
-        WarningCollection {
-            warnings: result,
-            names,
-            files,
-            directories,
-            keywords,
+fn warning_from_captures(
+    cap: &Captures,
+    toolchain: &str,
+    flag_prefixes: &[&str],
+    cwd: &Path,
+    options: &ParseOptions,
+    timer: Option<&Cell<Duration>>,
+) -> Warning {
+    let group = |name| cap.name(name).map(|capture| capture.as_str());
+    let number = |name| group(name).and_then(|text| text.parse().ok());
+
+    // gfortran puts the source line and its markers before the message
+    let source_and_underline = match (group("text_after"), group("underline")) {
+        (Some(source), Some(underline)) => Some((source, underline)),
+        _ => group("text_before").and_then(|text| {
+            let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+            Some((lines.next()?, lines.next()?))
+        }),
+    };
+    let underlined = match (options.keyword_underlined_only, source_and_underline) {
+        (true, Some((source, underline))) => {
+            let source = expand_tabs(source, options.tab_width);
+            underlined_text(&source, underline).map(str::to_string)
+        }
+        _ => None,
+    };
+
+    let name = ["name", "bracketed", "error", "remark"]
+        .into_iter()
+        .find_map(group)
+        .unwrap_or_default();
+    let name = flag_prefixes
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name);
+
+    // Only look for the message if it's needed, as it's another regex
+    let message = (options.keep_messages || options.keyword_source != KeywordSource::Code)
+        .then(|| warning_message(&cap[0]).or_else(|| group("text")))
+        .flatten();
+    let code = underlined
+        .as_deref()
+        .or_else(|| group("text_after"))
+        .or_else(|| group("text_before"))
+        .or_else(|| group("text"));
+    let keywords_in = |text: Option<&str>| match text {
+        Some(text) => timed(timer, || make_keywords(text, options)),
+        _ => Vec::new(),
+    };
+    let keywords = match options.keyword_source {
+        KeywordSource::Code => keywords_in(code),
+        KeywordSource::Message => keywords_in(message),
+        KeywordSource::Both => {
+            let mut keywords = keywords_in(code);
+            let from_message = keywords_in(message)
+                .into_iter()
+                .filter(|keyword| !keywords.contains(keyword))
+                .collect::<Vec<_>>();
+            keywords.extend(from_message);
+            keywords
         }
+    };
+
+    Warning {
+        name: name.to_string(),
+        file: resolve_file(group("file").unwrap_or_default(), cwd, options),
+        line: number("line"),
+        column: number("col"),
+        keywords,
+        toolchain: toolchain.to_string(),
+        raw: options.keep_raw.then(|| cap[0].to_string()),
+        message: message
+            .filter(|_| options.keep_messages)
+            .map(str::to_string),
     }
+}
 
-    pub fn diff(&self, other: &WarningCollection) -> WarningCollectionDiff {
-        WarningCollectionDiff {
-            names: diff_hashmaps(&self.names, &other.names),
-            files: diff_hashmaps(&self.files, &other.files),
-            directories: diff_hashmaps(&self.directories, &other.directories),
-            keywords: diff_hashmaps(&self.keywords, &other.keywords),
+/// Run `f`, adding the time it took to `timer` if there is one
+fn timed<T, F: FnOnce() -> T>(timer: Option<&Cell<Duration>>, f: F) -> T {
+    let Some(timer) = timer else {
+        return f();
+    };
+    let start = Instant::now();
+    let result = f();
+    timer.set(timer.get() + start.elapsed());
+    result
+}
+
+/// Prefixes of the flags in gcc and clang's warnings, errors, and remarks,
+/// longest first
+const GCC_FLAG_PREFIXES: &[&str] = &["-Werror=", "-W", "-R"];
+
+/// Check if `text` matches `pattern`, where `*` in `pattern` matches any
+/// sequence of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There's always at least one part, even for an empty pattern
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        // No wildcards, so must be an exact match
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
         }
     }
+    rest.ends_with(last)
+}
+
+/// The most common item in `counts`, ties broken by the smallest item
+fn most_common<T: Ord>(counts: &HashMap<T, i32>) -> Option<(&T, i32)> {
+    counts
+        .iter()
+        .max_by(|lhs, rhs| lhs.1.cmp(rhs.1).then_with(|| rhs.0.cmp(lhs.0)))
+        .map(|(item, &count)| (item, count))
 }
 
-fn diff_hashmaps<T>(lhs: &HashMap<T, i16>, rhs: &HashMap<T, i16>) -> HashMap<T, i16>
+fn count_warning_fn<F, T>(warnings: &[Warning], f: F) -> HashMap<T, i32>
 where
-    T: Eq + Hash + Clone,
+    F: Fn(&Warning) -> T,
+    T: Eq + std::hash::Hash,
 {
-    let mut result = lhs.clone();
+    let mut result = HashMap::new();
 
-    for (name, count) in rhs.iter() {
-        *result.entry(name.clone()).or_default() -= count;
+    for warning in warnings {
+        let count = result.entry(f(warning)).or_insert(0);
+        *count += 1;
     }
-    result.retain(|_, &mut value| value != 0);
+
     result
 }
 
-impl fmt::Display for WarningCollection {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let top_n = match f.precision() {
-            Some(precision) => precision,
-            _ => 10,
-        };
-
-        let names = make_warning_counts(&self.names, 0, false);
-        let files = make_warning_counts(&self.files, top_n, true);
-        let directories = make_warning_counts(&self.directories, top_n, true);
-        let keywords = make_warning_counts(&self.keywords, top_n, true);
-        write!(
-            f,
-            r#"Warnings:
-{names}
-
-Files:
-{files}
+/// Add all the `other` counts to `counts`
+fn add_counts<T: Eq + Hash>(counts: &mut HashMap<T, i32>, other: HashMap<T, i32>) {
+    for (key, count) in other {
+        let total = counts.entry(key).or_default();
+        *total = total.saturating_add(count);
+    }
+}
 
-Directories:
-{directories}
+/// The `counts` of paths with `prefix` stripped from them, where they
+/// start with it. The prefix itself becomes `.`
+fn stripped_keys(counts: &HashMap<PathBuf, i32>, prefix: &Path) -> HashMap<PathBuf, i32> {
+    let mut result = HashMap::new();
+    for (path, &count) in counts {
+        let path = match path.strip_prefix(prefix) {
+            Ok(stripped) if stripped.as_os_str().is_empty() => PathBuf::from("."),
+            Ok(stripped) => stripped.to_path_buf(),
+            Err(_) => path.clone(),
+        };
+        *result.entry(path).or_default() += count;
+    }
+    result
+}
 
-Keywords:
-{keywords}
-"#
-        )
+/// Multiply all the `counts` by `scale`, to estimate the full counts from
+/// a sample
+fn scaled<T>(mut counts: HashMap<T, i32>, scale: i32) -> HashMap<T, i32> {
+    if scale != 1 {
+        for count in counts.values_mut() {
+            *count = count.saturating_mul(scale);
+        }
     }
+    counts
 }
 
-fn make_warning_counts<T: AsRef<Path>>(
-    warnings: &HashMap<T, i16>,
-    top_n: usize,
-    use_total_items: bool,
-) -> String
+/// Keep only the `limit` most common of `counts`, ties broken by key, adding
+/// the rest up under `(other)`
+fn capped<T>(counts: &mut HashMap<T, i32>, limit: usize)
 where
-    T: Eq + Ord,
+    T: Eq + Hash + Ord + Clone + From<&'static str>,
 {
-    if warnings.is_empty() {
-        return String::new();
+    let other = T::from("(other)");
+    // Anything already under `(other)` stays there, rather than being one of
+    // the most common
+    let already = counts.remove(&other);
+    if counts.len() > limit {
+        let mut items = counts.drain().collect::<Vec<_>>();
+        items.sort_by(|lhs, rhs| rhs.1.cmp(&lhs.1).then_with(|| lhs.0.cmp(&rhs.0)));
+        let rest = items.split_off(limit);
+        counts.extend(items);
+        let total = rest
+            .into_iter()
+            .fold(already.unwrap_or(0), |total, (_, count)| {
+                total.saturating_add(count)
+            });
+        counts.insert(other, total);
+    } else if let Some(already) = already {
+        counts.insert(other, already);
     }
+}
 
-    let mut count_vec: Vec<_> = warnings.iter().collect();
-    count_vec.sort_by(|lhs, rhs| {
-        if lhs.1 == rhs.1 {
-            lhs.0.cmp(rhs.0)
-        } else {
-            lhs.1.cmp(rhs.1).reverse()
-        }
-    });
+fn count_warning_signatures(warnings: &[Warning]) -> HashMap<String, i32> {
+    count_warning_fn(warnings, Warning::signature)
+}
 
-    let max_length = if top_n == 0 {
-        count_vec.len()
-    } else {
-        std::cmp::min(count_vec.len(), top_n)
-    };
+/// Count the warnings from linkers by their messages
+fn count_linker_warnings(warnings: &[Warning]) -> HashMap<String, i32> {
+    let mut result = HashMap::new();
+    for warning in warnings.iter().filter(|warning| warning.toolchain == "ld") {
+        let message = warning.message.clone().unwrap_or_default();
+        *result.entry(message).or_default() += 1;
+    }
+    result
+}
 
-    let min_width = warnings.values().sum::<i16>().ilog10() as usize + 1;
+fn count_warning_types(warnings: &[Warning]) -> HashMap<String, i32> {
+    count_warning_fn(warnings, |warning| warning.name.clone())
+}
 
-    let result = count_vec
-        .iter()
-        .take(max_length)
-        .map(|line| format!(r"{1:0$}  {2}", min_width, line.1, line.0.as_ref().display()))
-        .fold(String::default(), |acc, line| format!("{acc}{line}\n"));
-    let extra = if count_vec.len() > top_n && top_n != 0 {
-        format!(
-            "{1:0$}  (+{2} more items)\n",
-            min_width,
-            " ",
-            count_vec.len() - top_n
-        )
-    } else {
-        "".to_string()
-    };
+fn count_warning_files(warnings: &[Warning]) -> HashMap<PathBuf, i32> {
+    let mut result = HashMap::new();
+    for warning in warnings.iter().filter(|warning| warning.has_file()) {
+        *result.entry(warning.file.clone()).or_default() += 1;
+    }
+    result
+}
 
-    let total: i16 = if use_total_items {
-        count_vec.len() as i16
-    } else {
-        warnings.values().sum()
-    };
-    let total_line = format!("{1:0$}  Total", min_width, total);
+/// Directory containing the file a warning appears in, using `.` for bare
+/// filenames so they still have a visible directory
+fn warning_directory(warning: &Warning) -> PathBuf {
+    match warning.file.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => PathBuf::from("."),
+        Some(parent) => parent.to_path_buf(),
+        None => warning.file.clone(),
+    }
+}
 
-    result + &extra + &total_line
+fn count_warning_directories(warnings: &[Warning]) -> HashMap<PathBuf, i32> {
+    let mut result = HashMap::new();
+    for warning in warnings.iter().filter(|warning| warning.has_file()) {
+        *result.entry(warning_directory(warning)).or_default() += 1;
+    }
+    result
 }
 
-// Helper function from https://stackoverflow.com/a/45145246
-#[cfg(test)]
-macro_rules! vec_of_strings {
+/// Count warnings by the name of their file, ignoring its directory, so
+/// that copies of the same file in different directories are counted
+/// together
+fn count_warning_basenames(warnings: &[Warning]) -> HashMap<PathBuf, i32> {
+    let mut result = HashMap::new();
+    for basename in warnings
+        .iter()
+        .filter_map(|warning| warning.file.file_name())
+    {
+        *result.entry(PathBuf::from(basename)).or_default() += 1;
+    }
+    result
+}
+
+/// Count warnings by their location as `file:line`, skipping any without
+/// a line number
+fn count_warning_lines(warnings: &[Warning]) -> HashMap<String, i32> {
+    let mut result = HashMap::new();
+    for warning in warnings {
+        if let Some(line) = warning.line {
+            let location = format!("{}:{line}", warning.file.display());
+            *result.entry(location).or_default() += 1;
+        }
+    }
+    result
+}
+
+fn count_warning_keywords(warnings: &[Warning], fold_case: bool) -> HashMap<String, i32> {
+    let keywords = warnings
+        .iter()
+        .flat_map(|warning| &warning.keywords)
+        .collect::<Vec<&String>>();
+
+    let mut result = HashMap::new();
+    for keyword in keywords {
+        let keyword = if fold_case {
+            keyword.to_lowercase()
+        } else {
+            keyword.clone()
+        };
+        *result.entry(keyword).or_default() += 1;
+    }
+
+    result
+}
+
+/// The distinct files with warnings in each directory
+fn files_per_directory(warnings: &[Warning]) -> HashMap<PathBuf, HashSet<&PathBuf>> {
+    let mut result: HashMap<PathBuf, HashSet<&PathBuf>> = HashMap::new();
+    for warning in warnings.iter().filter(|warning| warning.has_file()) {
+        result
+            .entry(warning_directory(warning))
+            .or_default()
+            .insert(&warning.file);
+    }
+    result
+}
+
+/// Section of directories as a tree, each indented under its parent, with
+/// the number of warnings in the directory and all its subdirectories, each
+/// warning counting `scale` times
+fn tree_section(warnings: &[Warning], scale: i32) -> Section {
+    let warnings = warnings
+        .iter()
+        .filter(|warning| warning.has_file())
+        .collect::<Vec<_>>();
+    if warnings.is_empty() {
+        return Section::default();
+    }
+
+    // Paths sort component by component, so parents come just before
+    // their children
+    let mut tree: BTreeMap<PathBuf, i32> = BTreeMap::new();
+    for warning in &warnings {
+        for directory in warning_directory(warning).ancestors() {
+            if !directory.as_os_str().is_empty() {
+                let count = tree.entry(directory.to_path_buf()).or_default();
+                *count = count.saturating_add(scale);
+            }
+        }
+    }
+
+    let total = (warnings.len() as i32).saturating_mul(scale).to_string();
+    let width = total.len();
+    let lines = tree
+        .iter()
+        .map(|(directory, count)| {
+            let depth = directory.components().count() - 1;
+            let name = directory
+                .file_name()
+                .map_or(directory.as_os_str(), |name| name)
+                .to_string_lossy();
+            format!("{count:>width$}  {:depth$}{name}", "", depth = depth * 2)
+        })
+        .collect();
+
+    Section {
+        lines,
+        extra: None,
+        total: Some(format!("{total}  Total")),
+        indent: width + 2,
+        sum: (warnings.len() as i32).saturating_mul(scale),
+        items: tree
+            .iter()
+            .map(|(directory, &count)| (directory.display().to_string(), count))
+            .collect(),
+    }
+}
+
+/// Number of warnings with each keyword, for each warning name, counting
+/// each keyword once per warning
+fn count_keyword_names(
+    warnings: &[Warning],
+    fold_case: bool,
+) -> HashMap<String, HashMap<String, i32>> {
+    let mut result: HashMap<String, HashMap<String, i32>> = HashMap::new();
+    for warning in warnings {
+        let keywords = warning
+            .keywords
+            .iter()
+            .map(|keyword| {
+                if fold_case {
+                    keyword.to_lowercase()
+                } else {
+                    keyword.clone()
+                }
+            })
+            .collect::<HashSet<_>>();
+        for keyword in keywords {
+            *result
+                .entry(keyword)
+                .or_default()
+                .entry(warning.name.clone())
+                .or_default() += 1;
+        }
+    }
+    result
+}
+
+/// The `section` of the most common keywords, each followed by the warning
+/// names it comes up in most, each warning counting `scale` times and
+/// grouped by `separator`
+fn themes_section(
+    collection: &WarningCollection,
+    section: Section,
+    scale: i32,
+    separator: Option<char>,
+) -> Section {
+    const NAMES_PER_KEYWORD: usize = 3;
+
+    let themes = count_keyword_names(&collection.warnings, collection.fold_keyword_case);
+    let lines = section
+        .lines
+        .iter()
+        .zip(&section.items)
+        .map(|(line, (keyword, _))| {
+            let mut names = themes
+                .get(keyword)
+                .map(|names| names.iter().collect::<Vec<_>>())
+                .unwrap_or_default();
+            names.sort_by(|lhs, rhs| rhs.1.cmp(lhs.1).then_with(|| lhs.0.cmp(rhs.0)));
+            let names = names
+                .into_iter()
+                .take(NAMES_PER_KEYWORD)
+                .map(|(name, &count)| {
+                    let count = grouped_count(count.saturating_mul(scale), separator);
+                    format!("{name} ({count})")
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{line}: {names}")
+        })
+        .collect();
+    Section { lines, ..section }
+}
+
+/// Section of directories by their number of warnings per distinct file
+/// with warnings, most dense first, ties broken by directory
+fn density_section(warnings: &[Warning], top_n: usize) -> Section {
+    let located = warnings.iter().filter(|warning| warning.has_file()).count();
+    if located == 0 {
+        return Section::default();
+    }
+
+    let files_per_directory = files_per_directory(warnings);
+    let mut densities = count_warning_directories(warnings)
+        .into_iter()
+        .map(|(directory, count)| {
+            let files = files_per_directory[&directory].len();
+            (directory, count, f64::from(count) / files as f64)
+        })
+        .collect::<Vec<_>>();
+    densities.sort_by(|lhs, rhs| rhs.2.total_cmp(&lhs.2).then_with(|| lhs.0.cmp(&rhs.0)));
+
+    let files = files_per_directory
+        .values()
+        .map(HashSet::len)
+        .sum::<usize>();
+    let overall = format!("{:.1}", located as f64 / files as f64);
+    let shown = if top_n == 0 { densities.len() } else { top_n };
+    let width = densities
+        .iter()
+        .map(|(_, _, density)| format!("{density:.1}").len())
+        .chain([overall.len()])
+        .max()
+        .unwrap_or_default();
+
+    let lines = densities
+        .iter()
+        .take(shown)
+        .map(|(directory, _, density)| format!("{density:>width$.1}  {}", directory.display()))
+        .collect();
+    let extra = (densities.len() > shown)
+        .then(|| format!("{:width$}  (+{} more items)", " ", densities.len() - shown));
+    let items = densities
+        .iter()
+        .take(shown)
+        .map(|(directory, count, _)| (directory.display().to_string(), *count))
+        .collect();
+
+    Section {
+        lines,
+        extra,
+        total: Some(format!("{overall:>width$}  Overall")),
+        indent: width + 2,
+        sum: located as i32,
+        items,
+    }
+}
+
+/// Score each directory by its number of warnings times its number of
+/// distinct files with warnings
+fn score_warning_hotspots(warnings: &[Warning]) -> HashMap<PathBuf, i32> {
+    let files_per_directory = files_per_directory(warnings);
+    count_warning_directories(warnings)
+        .into_iter()
+        .map(|(directory, count)| {
+            let files = files_per_directory[&directory].len() as i32;
+            (directory, count.saturating_mul(files))
+        })
+        .collect()
+}
+
+/// Make a name for a NAG warning from its message, by dropping the
+/// uppercase identifiers it mentions, so that "Unused local variable X"
+/// becomes "unused-local-variable"
+fn nag_warning_name(message: &str) -> String {
+    let is_identifier = |word: &&str| {
+        word.chars().any(|c| c.is_ascii_uppercase())
+            && !word.chars().any(|c| c.is_ascii_lowercase())
+    };
+    message
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .filter(|word| !is_identifier(word))
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+lazy_static! {
+    // Quoted identifiers, types, and so on in a message
+    static ref QUOTED_RE: Regex = Regex::new(r#"'[^']*'|"[^"]*"|`[^`]*`"#).unwrap();
+}
+
+/// The message on the first line of a warning, between the `warning:` and
+/// the name in brackets, if it's in gcc's or Intel's format
+fn warning_message(text: &str) -> Option<&str> {
+    lazy_static! {
+        static ref MESSAGE_RE: Regex = Regex::new(
+            r"^(?:[A-Za-z]:)?[^\s:][^:\n]*?(?::\d+(?::\d+)?:|\(\d+\):)\s*(?:[wW]arning|[eE]rror|[rR]emark)(?:\s*#\d+)?:\s*(?P<message>.*?)(?:\s*\[[^\]]*\])?\s*$"
+        )
+        .unwrap();
+    }
+    let first = text.lines().next()?;
+    MESSAGE_RE
+        .captures(first)
+        .and_then(|cap| cap.name("message"))
+        .map(|message| message.as_str())
+}
+
+/// `message` with quoted text replaced by `'...'` and numbers by `N`, so
+/// messages about the same problem in different places are the same
+fn message_template(message: &str) -> String {
+    lazy_static! {
+        static ref NUMBER_RE: Regex = Regex::new(r"\b\d+\b").unwrap();
+    }
+    let unquoted = QUOTED_RE.replace_all(message, "'...'");
+    NUMBER_RE.replace_all(&unquoted, "N").into_owned()
+}
+
+/// Name for a warning without a flag, such as from Swift, made from the
+/// first `words` words of its message, leaving out anything quoted
+fn message_warning_name(message: &str, words: usize) -> String {
+    QUOTED_RE
+        .replace_all(message, " ")
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .take(words)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Whether the text after an opening parenthesis in a LaTeX log is a file
+/// being read, rather than something like "(12.3pt too wide)"
+fn is_latex_file(token: &str) -> bool {
+    token.starts_with('/')
+        || token.starts_with("./")
+        || token.starts_with("../")
+        || (token.contains('.') && token.starts_with(|c: char| c.is_alphabetic()))
+}
+
+/// LaTeX warnings, which don't say which file they are in. Instead, the log
+/// has `(file` when TeX starts reading a file and `)` when it's done, so
+/// the current file is tracked with a stack, which carries over from one
+/// part of a log to the next
+fn find_latex_warnings<'a>(
+    content: &'a str,
+    files: &mut Vec<Option<String>>,
+) -> Vec<LatexWarning<'a>> {
+    lazy_static! {
+        static ref WARNING_RE: Regex = Regex::new(
+            r"^(?:(?P<kind>Package|Class) (?P<package>\S+)|LaTeX(?: (?P<latex>\S+))?) Warning: (?P<text>.*)"
+        )
+        .unwrap();
+        static ref BOX_RE: Regex =
+            Regex::new(r"^(?P<name>(?:Over|Under)full) \\(?P<box>[hv]box)(?P<text>.*)").unwrap();
+    }
+
+    let mut result = Vec::new();
+    for line in content.lines() {
+        let offset = line.as_ptr() as usize - content.as_ptr() as usize;
+        let current_file = files.iter().rev().find_map(Option::as_deref);
+        let name_and_text = if let Some(cap) = WARNING_RE.captures(line) {
+            let name = match (cap.name("kind"), cap.name("package"), cap.name("latex")) {
+                (Some(kind), Some(package), _) => format!("{}-{}", kind.as_str(), package.as_str()),
+                (_, _, Some(latex)) => format!("LaTeX-{}", latex.as_str()),
+                _ => "LaTeX".to_string(),
+            };
+            Some((name, cap.name("text").map_or("", |text| text.as_str())))
+        } else {
+            BOX_RE.captures(line).map(|cap| {
+                (
+                    format!("{}-{}", &cap["name"], &cap["box"]),
+                    cap.name("text").map_or("", |text| text.as_str()),
+                )
+            })
+        };
+
+        if let (Some((name, text)), Some(file)) = (name_and_text, current_file) {
+            result.push(LatexWarning {
+                offset,
+                name,
+                file: file.to_string(),
+                text,
+            });
+        }
+
+        for (index, c) in line.char_indices() {
+            match c {
+                '(' => {
+                    let rest = &line[index + 1..];
+                    let end = rest
+                        .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                        .unwrap_or(rest.len());
+                    let token = &rest[..end];
+                    files.push(is_latex_file(token).then(|| token.to_string()));
+                }
+                ')' => {
+                    files.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    result
+}
+
+/// A LaTeX warning found by [`find_latex_warnings`], in the file LaTeX was
+/// reading at the time
+struct LatexWarning<'a> {
+    /// Where the warning starts in the log
+    offset: usize,
+    name: String,
+    file: String,
+    text: &'a str,
+}
+
+impl LatexWarning<'_> {
+    fn warning(
+        &self,
+        cwd: &Path,
+        options: &ParseOptions,
+        timer: Option<&Cell<Duration>>,
+    ) -> Warning {
+        lazy_static! {
+            static ref LINE_RE: Regex = Regex::new(r"(?:input line|lines?) (?P<line>\d+)").unwrap();
+        }
+
+        Warning {
+            name: self.name.clone(),
+            file: resolve_file(&self.file, cwd, options),
+            line: LINE_RE
+                .captures(self.text)
+                .and_then(|cap| cap["line"].parse().ok()),
+            column: None,
+            keywords: timed(timer, || make_keywords(self.text, options)),
+            toolchain: "latex".to_string(),
+            raw: None,
+            message: options.keep_messages.then(|| self.text.to_string()),
+        }
+    }
+}
+
+/// Where each of `caps` starts in the text it was found in
+fn match_starts(caps: &[Captures]) -> Vec<usize> {
+    caps.iter()
+        .map(|cap| cap.get(0).map_or(0, |mat| mat.start()))
+        .collect()
+}
+
+/// Counts warnings as they are parsed, to stop parsing once there are too
+/// many of them, or they are in too many different files
+struct Limits {
+    max_files: Option<usize>,
+    max_warnings: Option<usize>,
+    files: HashSet<PathBuf>,
+    warnings: usize,
+    /// Whether any warnings have been turned away
+    truncated: bool,
+}
+
+impl Limits {
+    fn new(options: &ParseOptions) -> Limits {
+        Limits {
+            max_files: options.max_files,
+            max_warnings: options.max_warnings,
+            files: HashSet::new(),
+            warnings: 0,
+            truncated: false,
+        }
+    }
+
+    /// Whether no more warnings can be admitted at all, so there's no
+    /// need to make them
+    fn is_full(&mut self) -> bool {
+        self.truncated =
+            self.truncated || self.max_warnings.is_some_and(|max| self.warnings >= max);
+        self.truncated
+    }
+
+    /// Check if `warning` is within the limits, recording it if so
+    fn admit(&mut self, warning: &Warning) -> bool {
+        let new_file = warning.has_file() && !self.files.contains(&warning.file);
+        self.truncated = self.truncated
+            || self.max_warnings.is_some_and(|max| self.warnings >= max)
+            || (new_file && self.max_files.is_some_and(|max| self.files.len() >= max));
+        if self.truncated {
+            return false;
+        }
+
+        self.warnings += 1;
+        if new_file && self.max_files.is_some() {
+            self.files.insert(warning.file.clone());
+        }
+        true
+    }
+}
+
+/// Run all the parsers over `content`
+fn parse_warnings(content: &str, options: &ParseOptions) -> Vec<Warning> {
+    let mut ingest = Ingest::new(options);
+    ingest.parse(content, |_| {});
+    ingest.into_warnings().0
+}
+
+/// Incrementally parses a log as it arrives in chunks, such as when it is
+/// being read from slow storage, giving the same warnings as parsing the
+/// whole log at once. Made by [`WarningCollection::ingest`]
+///
+/// Custom parsers that match more than one line may miss warnings that
+/// straddle two chunks
+pub struct Ingest<'a> {
+    options: &'a ParseOptions,
+    cwd: PathBuf,
+    /// Text that hasn't been parsed yet, because it may end partway
+    /// through a warning
+    pending: String,
+    /// Warnings from each parser in turn, so that they end up in the same
+    /// order as when the whole log is parsed at once
+    parsed: Vec<Vec<Warning>>,
+    /// Indices of warnings from the gcc parser without numbered source
+    /// lines, which are from clang if this turns out to be a clang log
+    maybe_clang: Vec<usize>,
+    clang_log: bool,
+    /// Whether the last gcc warning of the chunk before was kept, rather
+    /// than sampled out, for notes at the start of the next chunk
+    last_gcc_kept: bool,
+    /// Files LaTeX is part way through reading
+    latex_files: Vec<Option<String>>,
+    limits: Limits,
+    /// Time spent so far, with [`ParseOptions::profile`]
+    profile: Option<Profile>,
+    keyword_time: Cell<Duration>,
+    /// Counts of the warnings so far, with
+    /// [`WarningCollection::aggregate_only`], which doesn't keep them
+    aggregate: Option<WarningCollection>,
+    /// Number of warnings found so far, before sampling
+    found: usize,
+}
+
+impl<'a> Ingest<'a> {
+    fn new(options: &'a ParseOptions) -> Ingest<'a> {
+        Ingest {
+            options,
+            cwd: current_dir().unwrap_or(PathBuf::from("")),
+            pending: String::new(),
+            parsed: vec![Vec::new(); 8 + options.custom_parsers.len()],
+            maybe_clang: Vec::new(),
+            clang_log: false,
+            last_gcc_kept: false,
+            latex_files: Vec::new(),
+            limits: Limits::new(options),
+            profile: options.profile.then(Profile::default),
+            keyword_time: Cell::default(),
+            aggregate: None,
+            found: 0,
+        }
+    }
+
+    /// The warnings starting at `offsets`, one list for each parser, as
+    /// their parser and index in log order, with only every
+    /// [`ParseOptions::sample_every`]th, counting on from the chunks
+    /// before, or all of them
+    fn in_log_order(&mut self, offsets: &[Vec<usize>]) -> Vec<(usize, usize)> {
+        let mut order = offsets
+            .iter()
+            .enumerate()
+            .flat_map(|(parser, offsets)| {
+                offsets
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, &offset)| (offset, parser, index))
+            })
+            .collect::<Vec<_>>();
+        order.sort_unstable();
+        let every = self.options.sample_every.unwrap_or(1).max(1);
+        let mut kept = Vec::with_capacity(order.len().div_ceil(every));
+        for (_, parser, index) in order {
+            if self.found.is_multiple_of(every) {
+                kept.push((parser, index));
+            }
+            self.found += 1;
+        }
+        kept
+    }
+
+    /// With [`WarningCollection::aggregate_only`], add the warnings parsed
+    /// so far to the counts and let them go
+    fn flush(&mut self) {
+        let Some(aggregate) = &mut self.aggregate else {
+            return;
+        };
+        let warnings = self
+            .parsed
+            .iter_mut()
+            .flat_map(std::mem::take)
+            .collect::<Vec<_>>();
+        self.maybe_clang.clear();
+        let chunk = WarningCollection::from_warnings(warnings, self.options);
+        add_counts(&mut aggregate.names, chunk.names);
+        add_counts(&mut aggregate.files, chunk.files);
+        add_counts(&mut aggregate.directories, chunk.directories);
+        add_counts(&mut aggregate.basenames, chunk.basenames);
+        add_counts(&mut aggregate.keywords, chunk.keywords);
+    }
+
+    /// Add the next `chunk` of the log, parsing as much as possible
+    pub fn push(&mut self, chunk: &str) {
+        // Only the new text, and the line it carries on, can have a start
+        let from = self.pending.rfind('\n').map_or(0, |index| index + 1);
+        self.pending.push_str(chunk);
+        let Some(split) = START_RE
+            .find_iter(&self.pending[from..])
+            .map(|start| from + start.start())
+            .filter(|&start| start > 0)
+            .last()
+        else {
+            return;
+        };
+        let rest = self.pending.split_off(split);
+        let complete = std::mem::replace(&mut self.pending, rest);
+        self.parse(&complete, |_| {});
+    }
+
+    /// Parse whatever is left of the log, and count up all the warnings
+    pub fn finish(mut self) -> WarningCollection {
+        let pending = std::mem::take(&mut self.pending);
+        self.parse(&pending, |_| {});
+        self.flush();
+        let options = self.options;
+        let mut profile = self.profile.take().map(|profile| Profile {
+            parse: profile.parse.saturating_sub(self.keyword_time.get()),
+            keywords: self.keyword_time.get(),
+            ..profile
+        });
+        let start = profile.is_some().then(Instant::now);
+        let sample_every = options.sample_every.filter(|&every| every > 1);
+
+        let collection = if let Some(aggregate) = self.aggregate.take() {
+            let mut collection = WarningCollection {
+                truncated: self.limits.truncated,
+                sample_every,
+                ..aggregate
+            };
+            let scale = collection.scale();
+            collection.names = scaled(std::mem::take(&mut collection.names), scale);
+            collection.files = scaled(std::mem::take(&mut collection.files), scale);
+            collection.directories = scaled(std::mem::take(&mut collection.directories), scale);
+            collection.basenames = scaled(std::mem::take(&mut collection.basenames), scale);
+            collection.keywords = scaled(std::mem::take(&mut collection.keywords), scale);
+            collection
+        } else {
+            let (warnings, truncated) = self.into_warnings();
+            let mut collection = WarningCollection {
+                truncated,
+                sample_every,
+                ..WarningCollection::from_warnings(warnings, options)
+            };
+            collection.recount();
+            collection
+        };
+        if let (Some(profile), Some(start)) = (&mut profile, start) {
+            profile.count = start.elapsed();
+        }
+        WarningCollection {
+            profile,
+            ..collection
+        }
+    }
+
+    /// All the warnings in order, and whether any were left out because of
+    /// the limits
+    fn into_warnings(mut self) -> (Vec<Warning>, bool) {
+        // Clang doesn't number its source snippets like gcc does, but does
+        // summarise how many warnings it found, often at the very end
+        if self.clang_log {
+            for &index in &self.maybe_clang {
+                self.parsed[0][index].toolchain = "clang".to_string();
+            }
+        }
+        let truncated = self.limits.truncated;
+        (self.parsed.into_iter().flatten().collect(), truncated)
+    }
+
+    /// Run all the parsers over `content`, which must end at the end of a
+    /// warning, calling `progress` with the number of bytes processed so
+    /// far
+    fn parse<F>(&mut self, content: &str, mut progress: F)
+    where
+        F: FnMut(usize),
+    {
+        lazy_static! {
+            static ref WARN_RE: Regex = Regex::new(
+                r"(?xm)
+            ^(?P<file>(?:[A-Za-z]:)?[^\s:][^:\n]*?):(?P<line>\d+):(?P<col>\d+):\s* # Filename, at the start of a line
+            (?P<text_before>\n\n\s+\d+\ \|.*\n.*\n)?     # Possible source code (gfortran)
+            (?:
+                [wW]arning:(?:.*?\[(?P<name>-W[^\]]*)|.*\[(?P<bracketed>[^\]]*))\] # Warning name, preferring a `-W` flag
+                | [eE]rror:.*?\[(?P<error>-Werror=[^\]]*)\]   # or one promoted to an error
+                | [rR]emark:.*?\[(?P<remark>-R[^\]]*)\]       # or a clang remark
+            )
+            [^\n]*                                        # Anything after the name
+            (?:
+                (?P<text_after>\n\s+\d+\ \|.*)           # Possible source code (gcc/clang)
+                (?P<underline>\n\s+\|\ *[~^].*)?          # and the underline beneath it
+            )?
+            "
+            )
+            .unwrap();
+            // Intel's ifort and icc, followed by the source line
+            static ref INTEL_RE: Regex = Regex::new(
+                r"(?m)^(?P<file>(?:[A-Za-z]:)?[^\s:][^:\n]*?)\((?P<line>\d+)\):\s*warning\s+#(?P<name>\d+):.*(?:\n(?P<text>.*))?"
+            )
+            .unwrap();
+            // gcc's notes for `#pragma message`, with or without quotes
+            static ref PRAGMA_RE: Regex = Regex::new(
+                r"(?m)^(?P<file>(?:[A-Za-z]:)?[^\s:][^:\n]*?):(?P<line>\d+):(?:(?P<col>\d+):)?\s*note:\s*'?(?P<name>#pragma message:\s*(?P<text>.*?))'?$"
+            )
+            .unwrap();
+            // NAG, which doesn't name its warnings
+            static ref NAG_RE: Regex = Regex::new(
+                r"(?m)^Warning:\s+(?P<file>[^,\n]+),\s+line\s+(?P<line>\d+):\s*(?P<text>.*)"
+            )
+            .unwrap();
+            // `go vet`, which doesn't name its warnings, and `staticcheck`,
+            // which ends them with the check ID
+            static ref GO_RE: Regex = Regex::new(
+                r"(?m)^(?P<file>(?:[A-Za-z]:)?[^\s:][^:\n]*?\.go):(?P<line>\d+):(?P<col>\d+):\s*(?P<text>.*?)(?:\s+\((?P<name>(?:SA|S|ST|QF|U)\d+)\))?$"
+            )
+            .unwrap();
+            // Warnings without a flag, which the gcc parser skips, such as
+            // Swift's from `xcodebuild`
+            static ref BRACKETLESS_RE: Regex = Regex::new(
+                r"(?m)^(?P<file>(?:[A-Za-z]:)?[^\s:][^:\n]*?):(?P<line>\d+):(?P<col>\d+):\s*warning:\s*(?P<text>.*?)\s*$"
+            )
+            .unwrap();
+            // Linkers, which rarely say where the problem is, so their
+            // warnings have no file
+            static ref LINKER_RE: Regex = Regex::new(
+                r"(?m)^(?:\S*/)?(?:ld|ld\.\w+|ld64(?:\.\w+)?|lld|lld-link|mold)(?:\.exe)?:\s+warning:\s*(?P<text>.*?)\s*$"
+            )
+            .unwrap();
+            // Notes about the warning before them, but not `#pragma message`
+            // notes, which are warnings of their own
+            static ref NOTE_RE: Regex = Regex::new(
+                r"(?m)^(?:[A-Za-z]:)?[^\s:][^:\n]*?:\d+:(?:\d+:)?\s*note:\s*(?P<text>.*)$"
+            )
+            .unwrap();
+        }
+
+        let start = self.profile.is_some().then(Instant::now);
+        let options = self.options;
+        let length = content.len();
+        let stripped = strip_build_prefixes(content);
+        let unwrapped = if options.unwrap_lines {
+            unwrap_lines(&stripped)
+        } else {
+            std::borrow::Cow::Borrowed(&*stripped)
+        };
+        let content = &*unwrapped;
+
+        self.clang_log |=
+            content.contains(" warning generated.") || content.contains(" warnings generated.");
+        let is_gfortran = |cap: &Captures| {
+            cap.name("text_before").is_some()
+                || cap[0].contains("Warning:")
+                || cap[0].contains("Error:")
+        };
+
+        // Find every warning before making any of them, so that sampling
+        // can skip all but every Nth in log order without the cost of
+        // finding their keywords
+        let gcc = WARN_RE.captures_iter(content).collect::<Vec<_>>();
+        let intel = INTEL_RE.captures_iter(content).collect::<Vec<_>>();
+        let nag = NAG_RE.captures_iter(content).collect::<Vec<_>>();
+        let go = GO_RE.captures_iter(content).collect::<Vec<_>>();
+        let latex = find_latex_warnings(content, &mut self.latex_files);
+        let bracketless = BRACKETLESS_RE
+            .captures_iter(content)
+            // Anything with a `[...]` after `warning:` is the gcc parser's
+            .filter(|cap| {
+                let text = &cap["text"];
+                !text
+                    .find('[')
+                    .is_some_and(|open| text[open..].contains(']'))
+            })
+            .collect::<Vec<_>>();
+        let pragmas = match options.include_pragmas {
+            true => PRAGMA_RE.captures_iter(content).collect(),
+            false => Vec::new(),
+        };
+        let linker = match options.include_linker {
+            true => LINKER_RE.captures_iter(content).collect(),
+            false => Vec::new(),
+        };
+        let custom = options
+            .custom_parsers
+            .iter()
+            .map(|parser| parser.regex.captures_iter(content).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut offsets = [&gcc, &intel, &nag, &go]
+            .into_iter()
+            .map(|caps| match_starts(caps))
+            .collect::<Vec<_>>();
+        offsets.push(latex.iter().map(|found| found.offset).collect());
+        offsets.extend([&bracketless, &pragmas, &linker].map(|caps| match_starts(caps)));
+        offsets.extend(custom.iter().map(|caps| match_starts(caps)));
+        let order = self.in_log_order(&offsets);
+
+        let Ingest {
+            cwd,
+            parsed,
+            limits,
+            keyword_time,
+            ..
+        } = self;
+        let timer = start.map(|_| &*keyword_time);
+
+        // Where each of this chunk's gcc warnings starts, and where it went
+        // if it was kept, to find the one before each note
+        let first_new = parsed[0].len();
+        let mut starts = offsets[0]
+            .iter()
+            .map(|&start| (start, None))
+            .collect::<Vec<_>>();
+        // Make the warnings in log order, so the limits keep the first ones
+        // in the log, from whichever parser
+        for (parser, index) in order {
+            if limits.is_full() {
+                break;
+            }
+            let warning = match parser {
+                0 => {
+                    let cap = &gcc[index];
+                    progress(cap.get(0).map_or(0, |mat| mat.end()));
+                    let toolchain = if cap.name("remark").is_some() {
+                        "clang"
+                    } else if is_gfortran(cap) {
+                        "gfortran"
+                    } else {
+                        "gcc"
+                    };
+                    warning_from_captures(cap, toolchain, GCC_FLAG_PREFIXES, cwd, options, timer)
+                }
+                1 => warning_from_captures(&intel[index], "intel", &[], cwd, options, timer),
+                2 => Warning {
+                    name: nag_warning_name(&nag[index]["text"]),
+                    ..warning_from_captures(&nag[index], "nag", &[], cwd, options, timer)
+                },
+                3 if go[index].name("name").is_some() => {
+                    warning_from_captures(&go[index], "staticcheck", &[], cwd, options, timer)
+                }
+                3 => Warning {
+                    name: "go-vet".to_string(),
+                    ..warning_from_captures(&go[index], "go-vet", &[], cwd, options, timer)
+                },
+                4 => latex[index].warning(cwd, options, timer),
+                5 => {
+                    let cap = &bracketless[index];
+                    let toolchain = if cap["file"].ends_with(".swift") {
+                        "swift"
+                    } else {
+                        "gcc"
+                    };
+                    Warning {
+                        name: message_warning_name(&cap["text"], options.message_name_words),
+                        ..warning_from_captures(cap, toolchain, &[], cwd, options, timer)
+                    }
+                }
+                6 => warning_from_captures(&pragmas[index], "gcc", &[], cwd, options, timer),
+                7 => Warning {
+                    name: "linker".to_string(),
+                    file: PathBuf::new(),
+                    line: None,
+                    column: None,
+                    keywords: Vec::new(),
+                    toolchain: "ld".to_string(),
+                    raw: options.keep_raw.then(|| linker[index][0].to_string()),
+                    message: Some(linker[index]["text"].to_string()),
+                },
+                _ => {
+                    let prefix = options.custom_parsers[parser - 8].flag_prefix.as_str();
+                    let cap = &custom[parser - 8][index];
+                    warning_from_captures(cap, "custom", &[prefix], cwd, options, timer)
+                }
+            };
+            if !limits.admit(&warning) {
+                break;
+            }
+            if parser == 0 {
+                if warning.toolchain == "gcc" && gcc[index].name("text_after").is_none() {
+                    self.maybe_clang.push(parsed[0].len());
+                }
+                starts[index].1 = Some(parsed[0].len());
+            }
+            parsed[parser].push(warning);
+        }
+
+        if options.include_notes_keywords {
+            for cap in NOTE_RE.captures_iter(content) {
+                let text = &cap["text"];
+                if text.trim_start_matches('\'').starts_with("#pragma message") {
+                    continue;
+                }
+                let note_start = cap.get(0).map_or(0, |mat| mat.start());
+                // Notes at the start of a chunk go with the last warning of
+                // the chunk before
+                let index = match starts.partition_point(|&(start, _)| start < note_start) {
+                    0 if self.last_gcc_kept => first_new.checked_sub(1),
+                    0 => None,
+                    before => starts[before - 1].1,
+                };
+                if let Some(warning) = index.and_then(|index| parsed[0].get_mut(index)) {
+                    let mut keywords = timed(timer, || make_keywords(text, options));
+                    if options.unique_keywords_per_warning {
+                        keywords.retain(|keyword| !warning.keywords.contains(keyword));
+                    }
+                    warning.keywords.extend(keywords);
+                }
+            }
+        }
+        if let Some(&(_, index)) = starts.last() {
+            self.last_gcc_kept = index.is_some();
+        }
+        progress(length);
+        if let (Some(profile), Some(start)) = (&mut self.profile, start) {
+            profile.parse += start.elapsed();
+        }
+    }
+}
+
+/// `content` with warnings that were hard-wrapped joined back onto one line.
+/// The lines after the start of a warning are only joined onto it if one of
+/// the next few ends with the `]` of its name, and none of them are source
+/// lines or the start of another warning
+fn unwrap_lines(content: &str) -> std::borrow::Cow<'_, str> {
+    /// Most lines a warning could have been wrapped onto after its first
+    const MAX_WRAPPED: usize = 4;
+
+    lazy_static! {
+        static ref DIAGNOSTIC_RE: Regex = Regex::new(
+            r"^(?:[A-Za-z]:)?[^\s:][^:\n]*?:\d+:\d+:\s*(?:[wW]arning|[eE]rror|[rR]emark):"
+        )
+        .unwrap();
+        static ref SOURCE_RE: Regex = Regex::new(r"^\s+(?:\d+\s*)?\|").unwrap();
+    }
+
+    fn without_newline(line: &str) -> &str {
+        line.trim_end_matches(['\n', '\r'])
+    }
+
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+    let mut result = String::with_capacity(content.len());
+    let mut joined = false;
+    let mut index = 0;
+    while let Some(&line) = lines.get(index) {
+        index += 1;
+        let first = without_newline(line);
+        if !DIAGNOSTIC_RE.is_match(first) || first.ends_with(']') {
+            result.push_str(line);
+            continue;
+        }
+        let end = lines[index..]
+            .iter()
+            .take(MAX_WRAPPED)
+            .take_while(|next| {
+                !next.trim().is_empty() && !START_RE.is_match(next) && !SOURCE_RE.is_match(next)
+            })
+            .position(|next| without_newline(next).trim_end().ends_with(']'));
+        let Some(end) = end else {
+            result.push_str(line);
+            continue;
+        };
+        result.push_str(first);
+        for &next in &lines[index..index + end] {
+            result.push_str(without_newline(next));
+        }
+        result.push_str(lines[index + end]);
+        index += end + 1;
+        joined = true;
+    }
+
+    if joined {
+        std::borrow::Cow::Owned(result)
+    } else {
+        std::borrow::Cow::Borrowed(content)
+    }
+}
+
+/// Time spent in each stage of summarising a log, with
+/// [`ParseOptions::profile`]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Profile {
+    /// Matching the parsers against the log, apart from finding keywords
+    pub parse: Duration,
+
+    /// Finding the keywords in each warning
+    pub keywords: Duration,
+
+    /// Counting the warnings in each category
+    pub count: Duration,
+
+    /// Rendering the summary, which is up to whoever renders it to time
+    pub render: Duration,
+}
+
+impl Profile {
+    fn add(&self, other: &Profile) -> Profile {
+        Profile {
+            parse: self.parse + other.parse,
+            keywords: self.keywords + other.keywords,
+            count: self.count + other.count,
+            render: self.render + other.render,
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages = [
+            ("parse", self.parse),
+            ("keywords", self.keywords),
+            ("count", self.count),
+            ("render", self.render),
+            (
+                "total",
+                self.parse + self.keywords + self.count + self.render,
+            ),
+        ];
+        for (stage, time) in stages {
+            writeln!(f, "{stage:<8}  {:>10}", format!("{time:.1?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Tags that build systems put at the start of lines, such as Bazel's
+/// `INFO: ` and `[1,234 / 5,678] `, and ninja's `[12/345] `
+const BUILD_PREFIX: &str = r"(?:INFO: |\[[\d,]+ / [\d,]+\] |\[\d+/\d+\] )";
+
+lazy_static! {
+    // No warning has a line like this part way through it, so it's always
+    // safe to split a log before one
+    static ref START_RE: Regex =
+        Regex::new(&format!(r"(?m)^{BUILD_PREFIX}*(?:[A-Za-z]:)?[^\s:][^:\n]*?(?::\d+:\d+:|\(\d+\):)")).unwrap();
+}
+
+/// `content` without any build system tags at the start of its lines, so
+/// they aren't taken to be part of filenames
+fn strip_build_prefixes(content: &str) -> std::borrow::Cow<'_, str> {
+    lazy_static! {
+        static ref BUILD_PREFIX_RE: Regex = Regex::new(&format!("(?m)^{BUILD_PREFIX}+")).unwrap();
+    }
+    BUILD_PREFIX_RE.replace_all(content, "")
+}
+
+/// The first `count` lines of `content`, carrying on to the end of the
+/// warning the last of them is part of
+pub fn first_lines(content: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    let end = content
+        .split_inclusive('\n')
+        .enumerate()
+        .take_while(|&(index, line)| index < count || !START_RE.is_match(line))
+        .map(|(_, line)| line.len())
+        .sum();
+    &content[..end]
+}
+
+/// The last `count` lines of `content`, going back to the start of the
+/// warning the first of them is part of
+pub fn last_lines(content: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+    let cut = lines.len().saturating_sub(count);
+    let first = lines[..lines.len().min(cut + 1)]
+        .iter()
+        .rposition(|line| START_RE.is_match(line))
+        .unwrap_or(cut);
+    let start = lines[..first].iter().map(|line| line.len()).sum::<usize>();
+    &content[start..]
+}
+
+/// Results of running just the parsers over a log, to check that a new log
+/// format is understood
+#[derive(Debug, PartialEq, Clone)]
+pub struct DryRun {
+    /// Number of matches from each parser
+    matches: Vec<(String, usize)>,
+
+    /// The first few files and warning names that were matched
+    samples: Vec<(PathBuf, String)>,
+}
+
+impl DryRun {
+    const SAMPLES: usize = 5;
+
+    pub fn new(content: &str, options: &ParseOptions) -> DryRun {
+        let warnings = parse_warnings(content, options);
+
+        let mut matches: Vec<(String, usize)> = Vec::new();
+        for warning in &warnings {
+            match matches
+                .iter_mut()
+                .find(|(toolchain, _)| *toolchain == warning.toolchain)
+            {
+                Some((_, count)) => *count += 1,
+                None => matches.push((warning.toolchain.clone(), 1)),
+            }
+        }
+
+        let samples = warnings
+            .into_iter()
+            .take(DryRun::SAMPLES)
+            .map(|warning| (warning.file, warning.name))
+            .collect();
+
+        DryRun { matches, samples }
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Matches:")?;
+        for (toolchain, count) in &self.matches {
+            writeln!(f, "{count}  {toolchain}")?;
+        }
+        writeln!(f, "\nSamples:")?;
+        for (file, name) in &self.samples {
+            writeln!(f, "{}  {name}", file.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// A `KEY=VALUE` line from a mapping file
+struct MappingLine {
+    /// 1-based line number in the file
+    line: usize,
+    key: String,
+    value: String,
+}
+
+impl MappingLine {
+    fn error(&self) -> Error {
+        Error::InvalidMapping {
+            line: self.line,
+            text: format!("{}={}", self.key, self.value),
+        }
+    }
+}
+
+/// Parse lines of `KEY=VALUE`, ignoring blank lines and `#` comments
+fn parse_mapping(content: &str) -> Result<Vec<MappingLine>, Error> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, text)| match text.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(MappingLine {
+                line,
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(Error::InvalidMapping {
+                line,
+                text: text.to_string(),
+            }),
+        })
+        .collect()
+}
+
+/// Check that `content` is all `KEY=VALUE` lines, without any key given
+/// twice. Parsing a mapping file keeps the last value for a key, so this
+/// is for checking a file is what was meant
+pub fn check_mapping(content: &str) -> Result<(), Error> {
+    let mut seen = HashMap::new();
+    for mapping in parse_mapping(content)? {
+        if let Some(&first) = seen.get(&mapping.key) {
+            return Err(Error::DuplicateMapping {
+                line: mapping.line,
+                first,
+                key: mapping.key,
+            });
+        }
+        seen.insert(mapping.key, mapping.line);
+    }
+    Ok(())
+}
+
+/// How much each warning name matters, for prioritising files
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Weights(HashMap<String, i32>);
+
+impl Weights {
+    /// Weight of warnings not given an explicit weight
+    const DEFAULT: i32 = 1;
+
+    fn get(&self, name: &str) -> i32 {
+        self.0.get(name).copied().unwrap_or(Weights::DEFAULT)
+    }
+}
+
+impl std::str::FromStr for Weights {
+    type Err = Error;
+
+    /// Parse lines of `NAME=WEIGHT`
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let mut weights = HashMap::new();
+        for mapping in parse_mapping(content)? {
+            let weight = mapping.value.parse().map_err(|_| mapping.error())?;
+            weights.insert(mapping.key, weight);
+        }
+        Ok(Weights(weights))
+    }
+}
+
+/// Ranges of numbers of warnings to group files by, each from a minimum to
+/// an optional maximum, inclusive
+#[derive(Debug, PartialEq, Clone)]
+pub struct Buckets(Vec<(i32, Option<i32>)>);
+
+impl Buckets {
+    fn label(&(min, max): &(i32, Option<i32>)) -> String {
+        match max {
+            Some(max) if max == min => min.to_string(),
+            Some(max) => format!("{min}-{max}"),
+            None => format!("{min}+"),
+        }
+    }
+}
+
+impl Default for Buckets {
+    /// `1,2-5,6-10,11-50,51+`
+    fn default() -> Self {
+        Buckets(vec![
+            (1, Some(1)),
+            (2, Some(5)),
+            (6, Some(10)),
+            (11, Some(50)),
+            (51, None),
+        ])
+    }
+}
+
+impl std::str::FromStr for Buckets {
+    type Err = Error;
+
+    /// Parse a comma-separated list of `N`, `N-M`, or `N+`
+    fn from_str(buckets: &str) -> Result<Self, Self::Err> {
+        buckets
+            .split(',')
+            .map(|bucket| {
+                let error = || Error::InvalidBucket(bucket.to_string());
+                let bucket = bucket.trim();
+                let range = if let Some(min) = bucket.strip_suffix('+') {
+                    (min.parse().map_err(|_| error())?, None)
+                } else if let Some((min, max)) = bucket.split_once('-') {
+                    let range = (
+                        min.parse().map_err(|_| error())?,
+                        max.parse().map_err(|_| error())?,
+                    );
+                    if range.1 < range.0 {
+                        return Err(error());
+                    }
+                    (range.0, Some(range.1))
+                } else {
+                    let count = bucket.parse().map_err(|_| error())?;
+                    (count, Some(count))
+                };
+                Ok(range)
+            })
+            .collect::<Result<_, _>>()
+            .map(Buckets)
+    }
+}
+
+/// Section of how many files have numbers of warnings in each of the
+/// `buckets`, including empty buckets, so the shape is easy to see
+fn distribution_section(files: &HashMap<PathBuf, i32>, buckets: &Buckets) -> Section {
+    if files.is_empty() {
+        return Section::default();
+    }
+
+    let items = buckets
+        .0
+        .iter()
+        .map(|bucket @ &(min, max)| {
+            let count = files
+                .values()
+                .filter(|&&count| count >= min && max.is_none_or(|max| count <= max))
+                .count();
+            (Buckets::label(bucket), count as i32)
+        })
+        .collect::<Vec<_>>();
+
+    let total = files.len().to_string();
+    let width = total.len();
+    let lines = items
+        .iter()
+        .map(|(label, count)| format!("{count:>width$}  {label}"))
+        .collect();
+
+    Section {
+        lines,
+        extra: None,
+        total: Some(format!("{total}  Total")),
+        indent: width + 2,
+        sum: files.len() as i32,
+        items,
+    }
+}
+
+/// Lines `start` to `end` of a file, inclusive. The file matches warnings
+/// in any file whose path ends with it, so `file.c` matches `src/file.c`
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineRange {
+    file: PathBuf,
+    start: usize,
+    end: usize,
+}
+
+impl LineRange {
+    fn contains(&self, warning: &Warning) -> bool {
+        warning
+            .line
+            .is_some_and(|line| (self.start..=self.end).contains(&line))
+            && warning.file.ends_with(&self.file)
+    }
+}
+
+impl std::str::FromStr for LineRange {
+    type Err = Error;
+
+    /// Parse `FILE:START-END`, or `FILE:LINE` for a single line
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let error = || Error::InvalidLineRange(range.to_string());
+        let (file, lines) = range.rsplit_once(':').ok_or_else(error)?;
+        let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+        let start = start.parse().map_err(|_| error())?;
+        let end = end.parse().map_err(|_| error())?;
+        if file.is_empty() || end < start {
+            return Err(error());
+        }
+        Ok(LineRange {
+            file: PathBuf::from(file),
+            start,
+            end,
+        })
+    }
+}
+
+/// Canonical names for warnings that are spelt differently by different
+/// compilers or versions, such as `uninitialized` for `maybe-uninitialized`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Aliases(HashMap<String, String>);
+
+impl Aliases {
+    /// Canonical name for `name`, following chains of aliases, so that
+    /// they can be given in any order
+    fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        let mut seen = HashSet::new();
+        let mut name = name;
+        while let Some(alias) = self.0.get(name) {
+            // Stop at the end of a cycle, rather than going round forever
+            if !seen.insert(name) {
+                break;
+            }
+            name = alias;
+        }
+        name
+    }
+}
+
+impl std::str::FromStr for Aliases {
+    type Err = Error;
+
+    /// Parse lines of `FROM=TO`
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let mut aliases = HashMap::new();
+        for mapping in parse_mapping(content)? {
+            if mapping.value.is_empty() {
+                return Err(mapping.error());
+            }
+            aliases.insert(mapping.key, mapping.value);
+        }
+        Ok(Aliases(aliases))
+    }
+}
+
+/// Score each file by the total weight of its warnings
+fn score_warning_files(warnings: &[Warning], weights: &Weights) -> HashMap<PathBuf, i32> {
+    let mut result: HashMap<PathBuf, i32> = HashMap::new();
+    for warning in warnings.iter().filter(|warning| warning.has_file()) {
+        let score = result.entry(warning.file.clone()).or_default();
+        *score = score.saturating_add(weights.get(&warning.name));
+    }
+    result
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CountDiff(i32);
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WarningCollectionDiff {
+    /// Mapping of warning names to counts
+    names: HashMap<String, i32>,
+
+    /// Mapping of filenames to counts
+    files: HashMap<PathBuf, i32>,
+
+    /// Mapping of directory names to counts
+    directories: HashMap<PathBuf, i32>,
+
+    /// Mapping of filenames, ignoring their directories, to counts
+    #[cfg_attr(feature = "serde", serde(default))]
+    basenames: HashMap<PathBuf, i32>,
+
+    /// Mapping of keywords to counts
+    keywords: HashMap<String, i32>,
+}
+
+impl WarningCollection {
+    pub fn new<T: AsRef<str>>(
+        content: &str,
+        keyword_len: usize,
+        ignored_keywords: &[T],
+    ) -> WarningCollection {
+        let options = ParseOptions {
+            keyword_len,
+            ignored_keywords: ignored_keywords
+                .iter()
+                .map(|keyword| keyword.as_ref().to_string())
+                .collect(),
+            ..Default::default()
+        };
+        WarningCollection::with_options(content, &options)
+    }
+
+    /// Parse warnings from `content` using the built-in parsers, followed by
+    /// any custom parsers in `options`
+    pub fn with_options(content: &str, options: &ParseOptions) -> WarningCollection {
+        WarningCollection::with_progress(content, options, |_| {})
+    }
+
+    /// Read and parse warnings from the log file at `path`
+    pub fn from_file(path: &Path, options: &ParseOptions) -> Result<WarningCollection, Error> {
+        let content = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(WarningCollection::with_options(&content, options))
+    }
+
+    /// As [`WarningCollection::with_options`], calling `progress` with the
+    /// number of bytes of `content` processed so far
+    pub fn with_progress<F>(content: &str, options: &ParseOptions, progress: F) -> WarningCollection
+    where
+        F: FnMut(usize),
+    {
+        let mut ingest = Ingest::new(options);
+        ingest.parse(content, progress);
+        ingest.finish()
+    }
+
+    /// As [`WarningCollection::with_options`], but only counting the
+    /// warnings, a chunk of the log at a time, without keeping them, for
+    /// huge logs. Everything that needs the individual warnings, such as
+    /// the lines and hotspots sections, is empty, and
+    /// [`recount`](WarningCollection::recount) and anything else that
+    /// recounts loses the counts. Notes can't add keywords to a warning in
+    /// an earlier chunk
+    pub fn aggregate_only(content: &str, options: &ParseOptions) -> WarningCollection {
+        const CHUNK_SIZE: usize = 1 << 16;
+
+        let mut ingest = Ingest::new(options);
+        ingest.aggregate = Some(WarningCollection::from_warnings(Vec::new(), options));
+        let mut start = 0;
+        while start < content.len() {
+            // Split before the first warning after the end of the next line
+            // past the chunk size, so no warning is split
+            let end = content.as_bytes()[(start + CHUNK_SIZE).min(content.len())..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .and_then(|newline| {
+                    let after = (start + CHUNK_SIZE).min(content.len()) + newline + 1;
+                    START_RE.find_at(content, after).map(|mat| mat.start())
+                })
+                .unwrap_or(content.len());
+            ingest.parse(&content[start..end], |_| {});
+            ingest.flush();
+            start = end;
+        }
+        ingest.finish()
+    }
+
+    /// Start parsing a log that arrives in chunks, giving each one to
+    /// [`Ingest::push`] and then calling [`Ingest::finish`] for the
+    /// collection
+    pub fn ingest(options: &ParseOptions) -> Ingest<'_> {
+        Ingest::new(options)
+    }
+
+    /// Read the warnings from the XML written by `cppcheck --xml`, in
+    /// either version of its format. Errors without a location, such as
+    /// missing includes, are skipped
+    pub fn from_cppcheck_xml(
+        content: &str,
+        options: &ParseOptions,
+    ) -> Result<WarningCollection, Error> {
+        use quick_xml::events::{BytesStart, Event};
+
+        let attribute = |element: &BytesStart, name: &str| -> Result<Option<String>, Error> {
+            match element
+                .try_get_attribute(name)
+                .map_err(quick_xml::Error::from)?
+            {
+                Some(value) => Ok(Some(value.unescape_value()?.into_owned())),
+                None => Ok(None),
+            }
+        };
+        let number = |element: &BytesStart, name: &str| -> Result<Option<usize>, Error> {
+            Ok(attribute(element, name)?.and_then(|value| value.parse().ok()))
+        };
+
+        let cwd = current_dir().unwrap_or(PathBuf::from(""));
+        let mut reader = quick_xml::Reader::from_str(content);
+        let mut warnings = Vec::new();
+        let mut current: Option<Warning> = None;
+        loop {
+            let event = reader.read_event()?;
+            match &event {
+                Event::Start(element) | Event::Empty(element) => {
+                    match element.local_name().as_ref() {
+                        b"error" => {
+                            let message = attribute(element, "msg")?.unwrap_or_default();
+                            let warning = Warning {
+                                name: attribute(element, "id")?.unwrap_or_default(),
+                                file: match attribute(element, "file")? {
+                                    Some(file) => resolve_file(&file, &cwd, options),
+                                    None => PathBuf::new(),
+                                },
+                                line: number(element, "line")?,
+                                column: None,
+                                keywords: make_keywords(&message, options),
+                                toolchain: "cppcheck".to_string(),
+                                raw: None,
+                                message: options.keep_messages.then_some(message),
+                            };
+                            if matches!(event, Event::Empty(_)) {
+                                warnings.push(warning);
+                            } else {
+                                current = Some(warning);
+                            }
+                        }
+                        // Only the first location is where the error is,
+                        // the others are the steps leading up to it
+                        b"location" => {
+                            if let Some(warning) = current.as_mut().filter(|w| w.line.is_none()) {
+                                let file = attribute(element, "file")?.unwrap_or_default();
+                                warning.file = resolve_file(&file, &cwd, options);
+                                warning.line = number(element, "line")?;
+                                warning.column = number(element, "column")?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::End(element) if element.local_name().as_ref() == b"error" => {
+                    warnings.extend(current.take());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        warnings.retain(|warning| !warning.file.as_os_str().is_empty());
+        Ok(WarningCollection::from_warnings(warnings, options))
+    }
+
+    fn from_warnings(mut warnings: Vec<Warning>, options: &ParseOptions) -> WarningCollection {
+        for warning in &mut warnings {
+            let name = options.aliases.resolve(&warning.name);
+            if name != warning.name {
+                warning.name = name.to_string();
+            }
+        }
+        warnings.retain(|warning| {
+            !options
+                .ignored_names
+                .iter()
+                .any(|pattern| glob_match(pattern, &warning.name))
+                && !options
+                    .excluded_ranges
+                    .iter()
+                    .any(|range| range.contains(warning))
+        });
+        let mut collection = WarningCollection {
+            warnings,
+            names: HashMap::new(),
+            files: HashMap::new(),
+            directories: HashMap::new(),
+            basenames: HashMap::new(),
+            keywords: HashMap::new(),
+            fold_keyword_case: options.fold_keyword_case,
+            truncated: false,
+            sample_every: None,
+            profile: None,
+        };
+        collection.recount();
+        collection
+    }
+
+    /// Recompute all the counts from the individual warnings, for example
+    /// after loading a collection from an untrusted source
+    pub fn recount(&mut self) {
+        let scale = self.scale();
+        self.names = scaled(count_warning_types(&self.warnings), scale);
+        self.files = scaled(count_warning_files(&self.warnings), scale);
+        self.directories = scaled(count_warning_directories(&self.warnings), scale);
+        self.basenames = scaled(count_warning_basenames(&self.warnings), scale);
+        self.keywords = scaled(
+            count_warning_keywords(&self.warnings, self.fold_keyword_case),
+            scale,
+        );
+    }
+
+    /// How much each warning counts for: N if only every Nth warning was
+    /// kept, otherwise 1
+    fn scale(&self) -> i32 {
+        self.sample_every
+            .map_or(1, |every| i32::try_from(every).unwrap_or(i32::MAX))
+    }
+
+    /// If only every Nth warning was kept, then N, so counts are estimates
+    pub fn sample_every(&self) -> Option<usize> {
+        self.sample_every
+    }
+
+    /// Add all the warnings from `other` to this collection. If either
+    /// side only has counts, as from [`from_counts`], the counts are added
+    /// up instead of recounted from the warnings
+    ///
+    /// [`from_counts`]: WarningCollection::from_counts
+    pub fn merge(&mut self, other: WarningCollection) {
+        self.truncated |= other.truncated;
+        self.sample_every = self.sample_every.or(other.sample_every);
+        self.profile = match (self.profile, other.profile) {
+            (Some(lhs), Some(rhs)) => Some(lhs.add(&rhs)),
+            (lhs, rhs) => lhs.or(rhs),
+        };
+        if self.is_counts_only() || other.is_counts_only() {
+            add_counts(&mut self.names, other.names);
+            add_counts(&mut self.files, other.files);
+            add_counts(&mut self.directories, other.directories);
+            add_counts(&mut self.basenames, other.basenames);
+            add_counts(&mut self.keywords, other.keywords);
+            self.warnings.extend(other.warnings);
+            return;
+        }
+        self.warnings.extend(other.warnings);
+        self.recount();
+    }
+
+    /// Counts in a category, keyed by how the items are displayed
+    fn display_counts(&self, category: Category) -> HashMap<String, i32> {
+        fn display<T: AsRef<Path>>(counts: &HashMap<T, i32>) -> HashMap<String, i32> {
+            counts
+                .iter()
+                .map(|(key, &count)| (key.as_ref().display().to_string(), count))
+                .collect()
+        }
+
+        match category {
+            Category::Names => self.names.clone(),
+            Category::Files => display(&self.files),
+            Category::Directories => display(&self.directories),
+            Category::Filenames => display(&self.basenames),
+            Category::Lines => count_warning_lines(&self.warnings),
+            Category::Keywords => self.keywords.clone(),
+            Category::Hotspots => display(&score_warning_hotspots(&self.warnings)),
+            Category::Signatures => count_warning_signatures(&self.warnings),
+            Category::Linker => count_linker_warnings(&self.warnings),
+            Category::Density
+            | Category::Tree
+            | Category::Scores
+            | Category::Themes
+            | Category::Distribution => HashMap::new(),
+        }
+    }
+
+    /// Files listed in `manifest`, one per line, that have no warnings.
+    /// Paths are resolved as they are when parsing, so that they match
+    pub fn clean_files(&self, manifest: &str, options: &ParseOptions) -> Vec<PathBuf> {
+        let cwd = current_dir().unwrap_or(PathBuf::from(""));
+        let mut clean = manifest
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| resolve_file(line, &cwd, options))
+            .filter(|file| !self.files.contains_key(file))
+            .collect::<Vec<_>>();
+        clean.sort();
+        clean.dedup();
+        clean
+    }
+
+    /// Build an index from warning names to warnings, for many queries
+    pub fn name_index(&self) -> NameIndex<'_> {
+        NameIndex::new(self)
+    }
+
+    /// The longest directory containing the files of all the warnings
+    pub fn common_prefix(&self) -> PathBuf {
+        let files = if self.is_counts_only() {
+            self.files.keys().collect::<Vec<_>>()
+        } else {
+            self.warnings.iter().map(|warning| &warning.file).collect()
+        };
+        let mut directories = files.into_iter().filter_map(|file| file.parent());
+        let Some(first) = directories.next() else {
+            return PathBuf::new();
+        };
+        let mut prefix = first.components().collect::<Vec<_>>();
+        for directory in directories {
+            let common = prefix
+                .iter()
+                .zip(directory.components())
+                .take_while(|(lhs, rhs)| *lhs == rhs)
+                .count();
+            prefix.truncate(common);
+        }
+        prefix.into_iter().collect()
+    }
+
+    /// Make the files of all warnings relative to their
+    /// [`common_prefix`](WarningCollection::common_prefix), returning the
+    /// prefix
+    pub fn strip_common_prefix(&mut self) -> PathBuf {
+        let prefix = self.common_prefix();
+        if self.is_counts_only() {
+            self.files = stripped_keys(&self.files, &prefix);
+            self.directories = stripped_keys(&self.directories, &prefix);
+            return prefix;
+        }
+        for warning in &mut self.warnings {
+            if let Ok(file) = warning.file.strip_prefix(&prefix) {
+                warning.file = file.to_path_buf();
+            }
+        }
+        self.recount();
+        prefix
+    }
+
+    /// As [`diff`](WarningCollection::diff), but comparing files by the
+    /// same number of trailing path components on both sides, so that logs
+    /// with absolute and relative paths, or from different machines, compare
+    /// the same. Enough components are kept to tell apart the files below
+    /// the [`common_prefix`](WarningCollection::common_prefix) of either
+    /// collection, so logs touching different directories still line up
+    pub fn diff_ignoring_path_base(&self, other: &WarningCollection) -> WarningCollectionDiff {
+        if other.is_names_only() {
+            return self.diff(other);
+        }
+        let depth = |collection: &WarningCollection| {
+            let prefix = collection.common_prefix();
+            collection
+                .warnings
+                .iter()
+                .filter_map(|warning| warning.file.strip_prefix(&prefix).ok())
+                .map(|file| file.components().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let depth = depth(self).max(depth(other));
+        let trimmed = |collection: &WarningCollection| {
+            let mut collection = collection.clone();
+            for warning in &mut collection.warnings {
+                warning.file = trailing_components(&warning.file, depth);
+            }
+            collection.recount();
+            collection
+        };
+        trimmed(self).diff(&trimmed(other))
+    }
+
+    /// Keep only the `limit` most common items in each category, adding the
+    /// rest up under `(other)`, to bound the size of a long-lived
+    /// collection. The individual warnings are kept, so
+    /// [`recount`](WarningCollection::recount) undoes this
+    pub fn cap_categories(&mut self, limit: usize) {
+        capped(&mut self.names, limit);
+        capped(&mut self.files, limit);
+        capped(&mut self.directories, limit);
+        capped(&mut self.basenames, limit);
+        capped(&mut self.keywords, limit);
+    }
+
+    /// Keep only the warnings with names in `keep`. If there are only
+    /// counts, the other categories can't be split up by name, so only the
+    /// names are left
+    pub fn retain_names(&mut self, keep: &HashSet<String>) {
+        if self.is_counts_only() {
+            self.names.retain(|name, _| keep.contains(name));
+            self.files.clear();
+            self.directories.clear();
+            self.basenames.clear();
+            self.keywords.clear();
+            return;
+        }
+        self.warnings.retain(|warning| keep.contains(&warning.name));
+        self.recount();
+    }
+
+    /// Remove any warnings with the same name, file, and line as one in
+    /// `baseline`, leaving only new warnings. Fails if either side only
+    /// has counts, with no lines to match
+    pub fn suppress_baseline(&mut self, baseline: &WarningCollection) -> Result<(), Error> {
+        if self.is_counts_only() || baseline.is_counts_only() {
+            return Err(Error::CountsOnly("suppress a baseline"));
+        }
+        let known = baseline
+            .warnings
+            .iter()
+            .map(|warning| (&warning.name, &warning.file, warning.line))
+            .collect::<HashSet<_>>();
+        self.warnings
+            .retain(|warning| !known.contains(&(&warning.name, &warning.file, warning.line)));
+        self.recount();
+        Ok(())
+    }
+
+    /// Warnings only in this collection, and only in `other`, matching
+    /// warnings by their name, file, and line
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a WarningCollection,
+    ) -> (Vec<&'a Warning>, Vec<&'a Warning>) {
+        let keys = |collection: &'a WarningCollection| {
+            collection
+                .warnings
+                .iter()
+                .map(|warning| (&warning.name, &warning.file, warning.line))
+                .collect::<HashSet<_>>()
+        };
+        let only_in = |collection: &'a WarningCollection, known: &HashSet<_>| {
+            collection
+                .warnings
+                .iter()
+                .filter(|warning| !known.contains(&(&warning.name, &warning.file, warning.line)))
+                .collect::<Vec<_>>()
+        };
+        (only_in(self, &keys(other)), only_in(other, &keys(self)))
+    }
+
+    /// Compilers or tools that produced the warnings, most common first
+    pub fn detected_toolchains(&self) -> Vec<String> {
+        let mut counts = count_warning_fn(&self.warnings, |warning| warning.toolchain.clone())
+            .into_iter()
+            .collect::<Vec<_>>();
+        counts.sort_by(|lhs, rhs| rhs.1.cmp(&lhs.1).then_with(|| lhs.0.cmp(&rhs.0)));
+        counts.into_iter().map(|(toolchain, _)| toolchain).collect()
+    }
+
+    /// Total weight of all the warnings
+    pub fn weighted_score(&self, weights: &Weights) -> i32 {
+        let score = self.warnings.iter().fold(0, |score: i32, warning| {
+            score.saturating_add(weights.get(&warning.name))
+        });
+        score.saturating_mul(self.scale())
+    }
+
+    /// A copy of all the counts, without the individual warnings
+    pub fn counts(&self) -> Counts {
+        Counts {
+            names: self.names.clone(),
+            files: self.files.clone(),
+            directories: self.directories.clone(),
+            basenames: self.basenames.clone(),
+            keywords: self.keywords.clone(),
+        }
+    }
+
+    /// A collection with just the given counts, and no individual
+    /// warnings. This can be diffed, merged, and summarised, but anything
+    /// that goes back to the warnings themselves, such as [`recount`],
+    /// leaves it empty
+    ///
+    /// [`recount`]: WarningCollection::recount
+    pub fn from_counts(counts: Counts) -> WarningCollection {
+        WarningCollection {
+            warnings: Vec::new(),
+            names: counts.names,
+            files: counts.files,
+            directories: counts.directories,
+            basenames: counts.basenames,
+            keywords: counts.keywords,
+            fold_keyword_case: false,
+            truncated: false,
+            sample_every: None,
+            profile: None,
+        }
+    }
+
+    /// Counts of each warning name
+    pub fn names(&self) -> &HashMap<String, i32> {
+        &self.names
+    }
+
+    /// Counts of warnings in each file
+    pub fn files(&self) -> &HashMap<PathBuf, i32> {
+        &self.files
+    }
+
+    /// Counts of warnings in each directory
+    pub fn directories(&self) -> &HashMap<PathBuf, i32> {
+        &self.directories
+    }
+
+    /// Counts of each keyword
+    pub fn keywords(&self) -> &HashMap<String, i32> {
+        &self.keywords
+    }
+
+    /// Whether parsing stopped early because of the limits in
+    /// [`ParseOptions`], so that some warnings are missing
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Total number of warnings
+    pub fn total(&self) -> i32 {
+        self.names.values().sum()
+    }
+
+    /// The total and the most common warning name, file, directory and
+    /// keyword, on a single line such as:
+    ///
+    /// ```text
+    /// 4 warnings | top: horrible-stuff(2), file src/net.c(2), dir src(3), keyword stuff(2)
+    /// ```
+    ///
+    /// Categories without any items are left out
+    pub fn oneline(&self) -> String {
+        let mut tops = Vec::new();
+        if let Some((name, count)) = most_common(&self.names) {
+            tops.push(format!("{name}({count})"));
+        }
+        if let Some((file, count)) = most_common(&self.files) {
+            tops.push(format!("file {}({count})", file.display()));
+        }
+        if let Some((directory, count)) = most_common(&self.directories) {
+            tops.push(format!("dir {}({count})", directory.display()));
+        }
+        if let Some((keyword, count)) = most_common(&self.keywords) {
+            tops.push(format!("keyword {keyword}({count})"));
+        }
+
+        let total = format!("{} warnings", self.total());
+        if tops.is_empty() {
+            total
+        } else {
+            format!("{total} | top: {}", tops.join(", "))
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            warnings: self.total(),
+            names: self.names.len(),
+            files: self.files.len(),
+            directories: self.directories.len(),
+            keywords: self.keywords.len(),
+        }
+    }
+
+    pub fn check_budget(&self, budget: &Budget) -> BudgetCheck {
+        let count = match &budget.name {
+            Some(name) => self.names.get(name).copied().unwrap_or(0),
+            None => self.total(),
+        };
+        BudgetCheck {
+            budget: budget.clone(),
+            count,
+        }
+    }
+
+    /// Warning names matching any of `patterns`, which may contain `*`
+    /// wildcards, along with their counts
+    pub fn find_names<T: AsRef<str>>(&self, patterns: &[T]) -> Vec<(String, i32)> {
+        let mut found = self
+            .names
+            .iter()
+            .filter(|(name, _)| {
+                patterns
+                    .iter()
+                    .any(|pattern| glob_match(pattern.as_ref(), name))
+            })
+            .map(|(name, &count)| (name.clone(), count))
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    }
+
+    /// Time spent parsing and counting the warnings, if the collection was
+    /// parsed with [`ParseOptions::profile`]
+    pub fn profile(&self) -> Option<Profile> {
+        self.profile
+    }
+
+    /// The text of the first warning called `name`, if the collection was
+    /// parsed with [`ParseOptions::keep_raw`]
+    pub fn explain(&self, name: &str) -> Option<&str> {
+        self.warnings
+            .iter()
+            .filter(|warning| warning.name == name)
+            .find_map(|warning| warning.raw.as_deref())
+    }
+
+    /// `source`, the contents of `file`, with a marker after each line for
+    /// each of its warnings. Warnings without a line come first, and any
+    /// past the end of `source` come last
+    pub fn annotate(&self, file: &Path, source: &str) -> String {
+        let mut warnings = self
+            .warnings
+            .iter()
+            .filter(|warning| warning.file == file)
+            .collect::<Vec<_>>();
+        warnings.sort_by_key(|warning| (warning.line, warning.column));
+
+        let marker = |warning: &Warning| {
+            let location = match (warning.line, warning.column) {
+                (Some(line), Some(column)) => format!(" {line}:{column}:"),
+                (Some(line), None) => format!(" {line}:"),
+                _ => String::new(),
+            };
+            format!(">>>{location} warning {}\n", warning.name)
+        };
+
+        let mut result = String::new();
+        let mut warnings = warnings.into_iter().peekable();
+        while let Some(warning) = warnings.next_if(|warning| warning.line.is_none()) {
+            result.push_str(&marker(warning));
+        }
+        for (index, line) in source.lines().enumerate() {
+            result.push_str(line);
+            result.push('\n');
+            while let Some(warning) = warnings.next_if(|warning| warning.line <= Some(index + 1)) {
+                result.push_str(&marker(warning));
+            }
+        }
+        for warning in warnings {
+            result.push_str(&marker(warning));
+        }
+        result
+    }
+
+    /// Whether there are counts but no individual warnings to recount them
+    /// from, as in a collection made by
+    /// [`from_counts`](WarningCollection::from_counts)
+    fn is_counts_only(&self) -> bool {
+        self.warnings.is_empty() && !self.names.is_empty()
+    }
+
+    /// Whether there are counts of names but nothing else, as in a
+    /// collection made from [`Counts::names_only`]
+    fn is_names_only(&self) -> bool {
+        self.warnings.is_empty()
+            && !self.names.is_empty()
+            && self.files.is_empty()
+            && self.directories.is_empty()
+            && self.basenames.is_empty()
+            && self.keywords.is_empty()
+    }
+
+    /// Changes in the counts since `other`. If `other` only has counts of
+    /// names, from [`Counts::names_only`], then only the names are compared
+    pub fn diff(&self, other: &WarningCollection) -> WarningCollectionDiff {
+        if other.is_names_only() {
+            return WarningCollectionDiff {
+                names: diff_hashmaps(&self.names, &other.names),
+                files: HashMap::new(),
+                directories: HashMap::new(),
+                basenames: HashMap::new(),
+                keywords: HashMap::new(),
+            };
+        }
+        WarningCollectionDiff {
+            names: diff_hashmaps(&self.names, &other.names),
+            files: diff_hashmaps(&self.files, &other.files),
+            directories: diff_hashmaps(&self.directories, &other.directories),
+            basenames: diff_hashmaps(&self.basenames, &other.basenames),
+            keywords: diff_hashmaps(&self.keywords, &other.keywords),
+        }
+    }
+
+    /// As [`diff`](WarningCollection::diff), but keeping the counts in
+    /// `other` and in this collection, to show alongside the changes
+    pub fn diff_detailed(&self, other: &WarningCollection) -> DetailedDiff {
+        DetailedDiff {
+            before: other.counts(),
+            after: self.counts(),
+            diff: self.diff(other),
+        }
+    }
+}
+
+/// The last `count` components of `path`, or all of them if it has fewer
+fn trailing_components(path: &Path, count: usize) -> PathBuf {
+    let components = path.components().collect::<Vec<_>>();
+    components[components.len().saturating_sub(count)..]
+        .iter()
+        .collect()
+}
+
+fn diff_hashmaps<T>(lhs: &HashMap<T, i32>, rhs: &HashMap<T, i32>) -> HashMap<T, i32>
+where
+    T: Eq + Hash + Clone,
+{
+    combine_hashmaps(lhs, rhs, -1)
+}
+
+fn add_hashmaps<T>(lhs: &HashMap<T, i32>, rhs: &HashMap<T, i32>) -> HashMap<T, i32>
+where
+    T: Eq + Hash + Clone,
+{
+    combine_hashmaps(lhs, rhs, 1)
+}
+
+/// Add `sign` times each count in `rhs` to `lhs`, dropping any entries that
+/// end up as zero
+fn combine_hashmaps<T>(lhs: &HashMap<T, i32>, rhs: &HashMap<T, i32>, sign: i32) -> HashMap<T, i32>
+where
+    T: Eq + Hash + Clone,
+{
+    let mut result = lhs.clone();
+
+    for (name, count) in rhs.iter() {
+        *result.entry(name.clone()).or_default() += sign * count;
+    }
+    result.retain(|_, &mut value| value != 0);
+    result
+}
+
+impl WarningCollectionDiff {
+    /// Keep only the changes in warning names that aren't in `baseline` at
+    /// all, rather than ones that have become more or less common
+    pub fn only_new_names(self, baseline: &WarningCollection) -> WarningCollectionDiff {
+        let mut names = self.names;
+        names.retain(|name, _| !baseline.names.contains_key(name));
+        WarningCollectionDiff {
+            names,
+            files: HashMap::new(),
+            directories: HashMap::new(),
+            basenames: HashMap::new(),
+            keywords: HashMap::new(),
+        }
+    }
+
+    /// Whether nothing has changed at all
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+            && self.files.is_empty()
+            && self.directories.is_empty()
+            && self.basenames.is_empty()
+            && self.keywords.is_empty()
+    }
+
+    /// Net change in the number of warnings
+    pub fn total(&self) -> i32 {
+        self.names.values().sum()
+    }
+
+    /// Files whose number of warnings has changed, by path, with the change
+    pub fn changed_files(&self) -> Vec<(&Path, i32)> {
+        let mut files = self
+            .files
+            .iter()
+            .map(|(file, &change)| (file.as_path(), change))
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    fn categories(&self) -> [(Category, Vec<(String, i32)>); 5] {
+        [
+            (Category::Names, sorted_changes(&self.names)),
+            (Category::Files, sorted_changes(&self.files)),
+            (Category::Directories, sorted_changes(&self.directories)),
+            (Category::Filenames, sorted_changes(&self.basenames)),
+            (Category::Keywords, sorted_changes(&self.keywords)),
+        ]
+    }
+}
+
+/// Changes as displayable strings, biggest first, ties broken by key
+fn sorted_changes<T>(changes: &HashMap<T, i32>) -> Vec<(String, i32)>
+where
+    T: AsRef<Path> + Ord,
+{
+    let mut changes = sorted_counts(changes);
+    changes.sort_by(|lhs, rhs| {
+        rhs.1
+            .unsigned_abs()
+            .cmp(&lhs.1.unsigned_abs())
+            .then_with(|| lhs.0.cmp(&rhs.0))
+    });
+    changes
+}
+
+/// How to present the changes in a [`WarningCollectionDiff`]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum DiffStyle {
+    /// Changes in the number of warnings, so fewer warnings are negative
+    #[default]
+    Arithmetic,
+
+    /// Reductions are shown as positive "fixed" counts, and increases as
+    /// negative "new" counts, so that bigger is better
+    Improvement,
+}
+
+/// A [`WarningCollectionDiff`] displayed in a particular [`DiffStyle`]
+pub struct DiffDisplay<'a> {
+    diff: &'a WarningCollectionDiff,
+    style: DiffStyle,
+    /// Collection the diff was taken against, to show the changes in
+    /// warning names as percentages of
+    baseline: Option<&'a WarningCollection>,
+}
+
+impl<'a> DiffDisplay<'a> {
+    /// Also show each change in the warning names as a percentage of its
+    /// count in `baseline`, which the diff must have been taken against
+    pub fn relative_to(self, baseline: &'a WarningCollection) -> DiffDisplay<'a> {
+        DiffDisplay {
+            baseline: Some(baseline),
+            ..self
+        }
+    }
+
+    /// Change to the warning called `name` as a percentage of its count in
+    /// the baseline, if there is one
+    fn relative(&self, name: &str, change: i32) -> Option<String> {
+        let before = self.baseline?.names.get(name).copied().unwrap_or(0);
+        if before == 0 {
+            return Some("(new)".to_string());
+        }
+        let percent = (f64::from(change) * 100.0 / f64::from(before)).round();
+        Some(format!("({percent:+}%)"))
+    }
+
+    fn change(&self, change: i32) -> String {
+        match self.style {
+            DiffStyle::Arithmetic => format!("{change:+}"),
+            DiffStyle::Improvement => format!("{:+}", -change),
+        }
+    }
+
+    fn label(&self, change: i32) -> &'static str {
+        match (self.style, change.signum()) {
+            (DiffStyle::Arithmetic, _) | (_, 0) => "",
+            (DiffStyle::Improvement, 1) => "  (new)",
+            (DiffStyle::Improvement, _) => "  (fixed)",
+        }
+    }
+}
+
+impl fmt::Display for DiffDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (category, changes) in self.diff.categories() {
+            if changes.is_empty() {
+                continue;
+            }
+            let width = changes
+                .iter()
+                .map(|&(_, change)| self.change(change).len())
+                .max()
+                .unwrap_or_default();
+            writeln!(f, "{}:", category.title())?;
+            for (key, change) in changes {
+                let label = self.label(change);
+                let relative = match category {
+                    Category::Names => self
+                        .relative(&key, change)
+                        .filter(|relative| !label.contains(relative.as_str()))
+                        .map(|relative| format!("  {relative}")),
+                    _ => None,
+                }
+                .unwrap_or_default();
+                writeln!(f, "{:>width$}  {key}{label}{relative}", self.change(change))?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "Net: {}", self.change(self.diff.total()))
+    }
+}
+
+impl WarningCollectionDiff {
+    /// Display the changes in the given style
+    pub fn display(&self, style: DiffStyle) -> DiffDisplay<'_> {
+        DiffDisplay {
+            diff: self,
+            style,
+            baseline: None,
+        }
+    }
+}
+
+impl fmt::Display for WarningCollectionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display(DiffStyle::Arithmetic))
+    }
+}
+
+impl std::ops::Add for WarningCollectionDiff {
+    type Output = WarningCollectionDiff;
+
+    fn add(self, other: WarningCollectionDiff) -> WarningCollectionDiff {
+        WarningCollectionDiff {
+            names: add_hashmaps(&self.names, &other.names),
+            files: add_hashmaps(&self.files, &other.files),
+            directories: add_hashmaps(&self.directories, &other.directories),
+            basenames: add_hashmaps(&self.basenames, &other.basenames),
+            keywords: add_hashmaps(&self.keywords, &other.keywords),
+        }
+    }
+}
+
+/// The changes in a [`WarningCollectionDiff`] along with the counts before
+/// and after, from [`WarningCollection::diff_detailed`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct DetailedDiff {
+    /// Counts in the baseline
+    before: Counts,
+
+    /// Counts now
+    after: Counts,
+
+    diff: WarningCollectionDiff,
+}
+
+/// The count of one item in the baseline and now
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DetailedChange {
+    pub key: String,
+    pub before: i32,
+    pub after: i32,
+}
+
+impl DetailedChange {
+    pub fn change(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+impl fmt::Display for DetailedChange {
+    /// As `key: before → after (change)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} \u{2192} {} ({:+})",
+            self.key,
+            self.before,
+            self.after,
+            self.change()
+        )
+    }
+}
+
+impl DetailedDiff {
+    /// Just the changes
+    pub fn diff(&self) -> &WarningCollectionDiff {
+        &self.diff
+    }
+
+    /// Items in `category` that have changed, biggest change first, ties
+    /// broken by key
+    pub fn changes(&self, category: Category) -> Vec<DetailedChange> {
+        let Some((_, changes)) = self
+            .diff
+            .categories()
+            .into_iter()
+            .find(|(changed, _)| *changed == category)
+        else {
+            return Vec::new();
+        };
+        let before = self.before.display_counts(category);
+        let after = self.after.display_counts(category);
+        changes
+            .into_iter()
+            .map(|(key, _)| DetailedChange {
+                before: before.get(&key).copied().unwrap_or(0),
+                after: after.get(&key).copied().unwrap_or(0),
+                key,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for DetailedDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (category, _) in self.diff.categories() {
+            let changes = self.changes(category);
+            if changes.is_empty() {
+                continue;
+            }
+            writeln!(f, "{}:", category.title())?;
+            for change in changes {
+                writeln!(f, "{change}")?;
+            }
+            writeln!(f)?;
+        }
+        let net = DetailedChange {
+            key: "Net".to_string(),
+            before: self.before.names.values().sum(),
+            after: self.after.names.values().sum(),
+        };
+        write!(f, "{net}")
+    }
+}
+
+/// A maximum number of warnings, either overall or for a single warning name
+#[derive(Debug, PartialEq, Clone)]
+pub struct Budget {
+    /// Warning name the budget applies to, or `None` for the total
+    name: Option<String>,
+
+    /// Maximum number of warnings allowed
+    limit: i32,
+}
+
+impl std::str::FromStr for Budget {
+    type Err = Error;
+
+    fn from_str(budget: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidBudget(budget.to_string());
+        let (name, limit) = match budget.rsplit_once('=') {
+            Some(("", _)) => return Err(invalid()),
+            Some((name, limit)) => (Some(name.to_string()), limit),
+            None => (None, budget),
+        };
+        let limit = limit.parse().map_err(|_| invalid())?;
+        if limit < 0 {
+            return Err(invalid());
+        }
+        Ok(Budget { name, limit })
+    }
+}
+
+/// Result of checking a [`Budget`] against a [`WarningCollection`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct BudgetCheck {
+    budget: Budget,
+
+    /// Number of warnings counted against the budget
+    count: i32,
+}
+
+impl BudgetCheck {
+    pub fn passed(&self) -> bool {
+        self.count <= self.budget.limit
+    }
+}
+
+impl fmt::Display for BudgetCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.passed() { "OK" } else { "FAIL" };
+        write!(f, "{status}: ")?;
+        if let Some(name) = &self.budget.name {
+            write!(f, "{name} ")?;
+        }
+        write!(f, "{}/{}", self.count, self.budget.limit)?;
+        if !self.passed() {
+            write!(f, " (over budget by {})", self.count - self.budget.limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// The counts in a [`WarningCollection`], without the individual warnings,
+/// for storing snapshots compactly. Empty counts are left out when
+/// serialized, so that [`names_only`](Counts::names_only) counts are tiny
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counts {
+    /// Mapping of warning names to counts
+    pub names: HashMap<String, i32>,
+
+    /// Mapping of filenames to counts
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "HashMap::is_empty")
+    )]
+    pub files: HashMap<PathBuf, i32>,
+
+    /// Mapping of directory names to counts
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "HashMap::is_empty")
+    )]
+    pub directories: HashMap<PathBuf, i32>,
+
+    /// Mapping of filenames, ignoring their directories, to counts
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "HashMap::is_empty")
+    )]
+    pub basenames: HashMap<PathBuf, i32>,
+
+    /// Mapping of keywords to counts
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "HashMap::is_empty")
+    )]
+    pub keywords: HashMap<String, i32>,
+}
+
+impl Counts {
+    /// Counts in a category, keyed by how the items are displayed
+    fn display_counts(&self, category: Category) -> HashMap<String, i32> {
+        fn display<T: AsRef<Path>>(counts: &HashMap<T, i32>) -> HashMap<String, i32> {
+            counts
+                .iter()
+                .map(|(key, &count)| (key.as_ref().display().to_string(), count))
+                .collect()
+        }
+
+        match category {
+            Category::Names => self.names.clone(),
+            Category::Files => display(&self.files),
+            Category::Directories => display(&self.directories),
+            Category::Filenames => display(&self.basenames),
+            Category::Keywords => self.keywords.clone(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Just the counts of names, leaving out anything about the code
+    /// itself, such as file paths and keywords, for a baseline that can be
+    /// shared
+    pub fn names_only(self) -> Counts {
+        Counts {
+            names: self.names,
+            ..Default::default()
+        }
+    }
+}
+
+/// How spread out the warnings in a [`WarningCollection`] are
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// Total number of warnings
+    pub warnings: i32,
+
+    /// Number of distinct warning names
+    pub names: usize,
+
+    /// Number of distinct files with warnings
+    pub files: usize,
+
+    /// Number of distinct directories with warnings
+    pub directories: usize,
+
+    /// Number of distinct keywords
+    pub keywords: usize,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} warnings: {} distinct warning types across {} files in {} directories, with {} distinct keywords",
+            self.warnings, self.names, self.files, self.directories, self.keywords
+        )
+    }
+}
+
+/// Average counts of each item over a series of collections, such as
+/// historical snapshots
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Averages(HashMap<Category, HashMap<String, f64>>);
+
+impl Averages {
+    /// Categories that can be averaged, which excludes scores as they
+    /// depend on the weights
+    const CATEGORIES: [Category; 7] = [
+        Category::Names,
+        Category::Files,
+        Category::Directories,
+        Category::Filenames,
+        Category::Lines,
+        Category::Keywords,
+        Category::Hotspots,
+    ];
+
+    /// Mean count of each item over `collections`, counting collections
+    /// without an item as zero
+    pub fn new(collections: &[WarningCollection]) -> Averages {
+        let mut averages = HashMap::new();
+        for category in Averages::CATEGORIES {
+            let mut sums: HashMap<String, f64> = HashMap::new();
+            for collection in collections {
+                for (item, count) in collection.display_counts(category) {
+                    *sums.entry(item).or_default() += f64::from(count);
+                }
+            }
+            for sum in sums.values_mut() {
+                *sum /= collections.len() as f64;
+            }
+            averages.insert(category, sums);
+        }
+        Averages(averages)
+    }
+}
+
+/// A category of items that warnings are counted by
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Category {
+    /// Warning names
+    Names,
+
+    /// Files with warnings
+    Files,
+
+    /// Directories with warnings
+    Directories,
+
+    /// Filenames with warnings, ignoring their directories
+    Filenames,
+
+    /// Source lines with warnings, as `file:line`
+    Lines,
+
+    /// Keywords from source lines with warnings
+    Keywords,
+
+    /// Directories scored by warnings times distinct files
+    Hotspots,
+
+    /// Directories by warnings per distinct file
+    Density,
+
+    /// Directories nested under their parents
+    Tree,
+
+    /// Files scored by the total weight of their warnings
+    Scores,
+
+    /// Keywords with the warnings they come up in most
+    Themes,
+
+    /// Numbers of files with numbers of warnings in different ranges
+    Distribution,
+
+    /// Warnings with the same name and message, wherever they are
+    Signatures,
+
+    /// Messages of warnings from linkers
+    Linker,
+}
+
+impl Category {
+    /// Every category, in the order they are shown by default
+    pub const ALL: [Category; 14] = [
+        Category::Names,
+        Category::Files,
+        Category::Directories,
+        Category::Filenames,
+        Category::Lines,
+        Category::Keywords,
+        Category::Hotspots,
+        Category::Density,
+        Category::Tree,
+        Category::Scores,
+        Category::Themes,
+        Category::Distribution,
+        Category::Signatures,
+        Category::Linker,
+    ];
+
+    /// Heading for this category's section in a summary
+    fn title(&self) -> &'static str {
+        match self {
+            Category::Names => "Warnings",
+            Category::Files => "Files",
+            Category::Directories => "Directories",
+            Category::Filenames => "Filenames",
+            Category::Lines => "Lines",
+            Category::Keywords => "Keywords",
+            Category::Hotspots => "Hotspots",
+            Category::Density => "Density",
+            Category::Tree => "Tree",
+            Category::Scores => "Scores",
+            Category::Themes => "Themes",
+            Category::Distribution => "Distribution",
+            Category::Signatures => "Signatures",
+            Category::Linker => "Linker",
+        }
+    }
+
+    /// Name of this category on the command line
+    fn name(&self) -> &'static str {
+        match self {
+            Category::Names => "names",
+            Category::Files => "files",
+            Category::Directories => "directories",
+            Category::Filenames => "filenames",
+            Category::Lines => "lines",
+            Category::Keywords => "keywords",
+            Category::Hotspots => "hotspots",
+            Category::Density => "density",
+            Category::Tree => "tree",
+            Category::Scores => "scores",
+            Category::Themes => "themes",
+            Category::Distribution => "distribution",
+            Category::Signatures => "signatures",
+            Category::Linker => "linker",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for Category {
+    type Err = Error;
+
+    fn from_str(category: &str) -> Result<Self, Self::Err> {
+        Category::ALL
+            .into_iter()
+            .find(|known| known.name() == category)
+            .ok_or_else(|| Error::InvalidCategory(category.to_string()))
+    }
+}
+
+/// How to order the items in a section of a summary
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    /// Most common first, ties broken by key
+    Count,
+
+    /// By the item itself: alphabetically for names, by path for files
+    Key,
+}
+
+/// How to order items with the same count in a section of a summary
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Tiebreak {
+    /// Alphabetically for names, by path for files
+    #[default]
+    Alpha,
+
+    /// In the order they first appear in the log
+    FirstSeen,
+}
+
+/// Options controlling which sections of a summary are shown, and how
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Top N items to display in each category, or all of them if zero
+    pub top_n: usize,
+
+    /// Categories to show sections for
+    pub sections: Vec<Category>,
+
+    /// Order to show the sections in. Any not listed come after, in the
+    /// order of [`Category::ALL`]
+    pub order: Vec<Category>,
+
+    /// Order of the "Warnings:" section
+    pub names_sort: SortOrder,
+
+    /// Order of the "Directories:" section. Paths are compared component by
+    /// component, so subdirectories come straight after their parents
+    pub dirs_sort: SortOrder,
+
+    /// Order of items with the same count
+    pub tiebreak: Tiebreak,
+
+    /// Character to group the thousands of counts with, such as `,` for
+    /// `12,345`, in the sections of counts
+    pub thousands_sep: Option<char>,
+
+    /// Prefix each line of the "Warnings:" section with its rank
+    pub ranked: bool,
+
+    /// Format for each line of the sections, instead of the count and the
+    /// item in columns
+    pub line_template: Option<LineTemplate>,
+
+    /// Count each warning name once per file in the "Warnings:" and
+    /// "Files:" sections, however many times it's repeated in the file
+    pub collapse_repeats: bool,
+
+    /// Show how many distinct items there are in each category
+    pub stats: bool,
+
+    /// Show directories scored by number of warnings times number of
+    /// distinct files with warnings
+    pub hotspots: bool,
+
+    /// Show directories by their number of warnings per distinct file with
+    /// warnings
+    pub density: bool,
+
+    /// Show directories as a tree, with the warnings in each subtree
+    pub tree: bool,
+
+    /// Show the most common keywords with the warnings they come up in most
+    pub themes: bool,
+
+    /// Show the most common warnings by name and message, wherever they
+    /// are, which needs [`ParseOptions::keep_messages`]
+    pub signatures: bool,
+
+    /// Show the messages of warnings from linkers, which needs
+    /// [`ParseOptions::include_linker`]
+    pub linker: bool,
+
+    /// Show files scored by the weights of their warnings
+    pub weights: Option<Weights>,
+
+    /// Show how many files have numbers of warnings in each of these
+    /// ranges
+    pub distribution: Option<Buckets>,
+
+    /// Show only the items in this category, without any headings
+    pub only: Option<Category>,
+
+    /// Include the total when showing only one category
+    pub with_total: bool,
+
+    /// Truncate items in the middle so that no line is wider than this
+    pub max_width: Option<usize>,
+
+    /// Show how far each count is from its historical average
+    pub averages: Option<Averages>,
+
+    /// Leave out the headings and the blank lines between sections
+    pub no_headers: bool,
+
+    /// Show only [`WarningCollection::oneline`], instead of any sections
+    pub oneline: bool,
+
+    /// Directory the paths have been made relative to, such as by
+    /// [`WarningCollection::strip_common_prefix`], to show at the top
+    pub relative_to: Option<PathBuf>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            top_n: 10,
+            sections: vec![
+                Category::Names,
+                Category::Files,
+                Category::Directories,
+                Category::Keywords,
+            ],
+            order: Vec::new(),
+            names_sort: SortOrder::Count,
+            dirs_sort: SortOrder::Count,
+            tiebreak: Tiebreak::Alpha,
+            thousands_sep: None,
+            ranked: false,
+            line_template: None,
+            collapse_repeats: false,
+            stats: false,
+            hotspots: false,
+            density: false,
+            tree: false,
+            themes: false,
+            signatures: false,
+            linker: false,
+            weights: None,
+            distribution: None,
+            only: None,
+            with_total: false,
+            max_width: None,
+            averages: None,
+            no_headers: false,
+            oneline: false,
+            relative_to: None,
+        }
+    }
+}
+
+/// A summary of a [`WarningCollection`] rendered with some [`ReportOptions`]
+pub struct Report<'a> {
+    collection: &'a WarningCollection,
+    options: &'a ReportOptions,
+}
+
+impl WarningCollection {
+    pub fn report<'a>(&'a self, options: &'a ReportOptions) -> Report<'a> {
+        Report {
+            collection: self,
+            options,
+        }
+    }
+}
+
+impl Report<'_> {
+    /// The first warning with each name in each file
+    fn first_occurrences(&self) -> Vec<Warning> {
+        let mut seen = HashSet::new();
+        self.collection
+            .warnings
+            .iter()
+            .filter(|warning| seen.insert((&warning.name, &warning.file)))
+            .cloned()
+            .collect()
+    }
+
+    /// Position of each item of `category` in the order they first
+    /// appear in the warnings
+    fn first_seen(&self, category: Category) -> HashMap<String, usize> {
+        let fold_case = self.collection.fold_keyword_case;
+        let mut first_seen = HashMap::new();
+        for warning in &self.collection.warnings {
+            let keys = match category {
+                Category::Names => vec![warning.name.clone()],
+                Category::Files | Category::Scores if warning.has_file() => {
+                    vec![warning.file.display().to_string()]
+                }
+                Category::Directories | Category::Hotspots | Category::Density | Category::Tree
+                    if warning.has_file() =>
+                {
+                    vec![warning_directory(warning).display().to_string()]
+                }
+                Category::Files
+                | Category::Scores
+                | Category::Directories
+                | Category::Hotspots
+                | Category::Density
+                | Category::Tree => Vec::new(),
+                Category::Filenames => warning
+                    .file
+                    .file_name()
+                    .map(|name| Path::new(name).display().to_string())
+                    .into_iter()
+                    .collect(),
+                Category::Lines => warning
+                    .line
+                    .map(|line| format!("{}:{line}", warning.file.display()))
+                    .into_iter()
+                    .collect(),
+                Category::Keywords | Category::Themes => warning
+                    .keywords
+                    .iter()
+                    .map(|keyword| {
+                        if fold_case {
+                            keyword.to_lowercase()
+                        } else {
+                            keyword.clone()
+                        }
+                    })
+                    .collect(),
+                Category::Signatures => vec![warning.signature()],
+                Category::Linker if warning.toolchain == "ld" => {
+                    warning.message.clone().into_iter().collect()
+                }
+                Category::Linker => Vec::new(),
+                Category::Distribution => Vec::new(),
+            };
+            for key in keys {
+                let next = first_seen.len();
+                first_seen.entry(key).or_insert(next);
+            }
+        }
+        first_seen
+    }
+
+    /// Section of `counts` for `category`, with ties broken as in the
+    /// options
+    fn make_section<T>(
+        &self,
+        category: Category,
+        counts: &HashMap<T, i32>,
+        top_n: usize,
+        use_total_items: bool,
+        sort: SortOrder,
+    ) -> Section
+    where
+        T: AsRef<Path> + Eq + Ord,
+    {
+        let first_seen = match self.options.tiebreak {
+            Tiebreak::Alpha => None,
+            Tiebreak::FirstSeen => Some(self.first_seen(category)),
+        };
+        make_section_by(
+            counts,
+            top_n,
+            use_total_items,
+            sort,
+            first_seen.as_ref(),
+            self.options.thousands_sep,
+        )
+    }
+
+    fn section(&self, category: Category) -> Section {
+        let mut section = self.untruncated_section(category);
+        if let Some(template) = &self.options.line_template {
+            section = section.templated(template, self.options.thousands_sep);
+        }
+        if let Some(max_width) = self.options.max_width {
+            section = section.truncated(max_width);
+        }
+        if let Some(averages) = self
+            .options
+            .averages
+            .as_ref()
+            .and_then(|averages| averages.0.get(&category))
+        {
+            section = section.versus_averages(averages);
+        }
+        section
+    }
+
+    fn untruncated_section(&self, category: Category) -> Section {
+        let collection = self.collection;
+        let top_n = self.options.top_n;
+        let scale = collection.scale();
+
+        match category {
+            Category::Names if self.options.collapse_repeats => {
+                let names = scaled(count_warning_types(&self.first_occurrences()), scale);
+                let section =
+                    self.make_section(category, &names, 0, false, self.options.names_sort);
+                if self.options.ranked {
+                    section.ranked()
+                } else {
+                    section
+                }
+            }
+            Category::Names => {
+                let section = self.make_section(
+                    category,
+                    &collection.names,
+                    0,
+                    false,
+                    self.options.names_sort,
+                );
+                if self.options.ranked {
+                    section.ranked()
+                } else {
+                    section
+                }
+            }
+            Category::Files if self.options.collapse_repeats => {
+                let files = scaled(count_warning_files(&self.first_occurrences()), scale);
+                self.make_section(category, &files, top_n, true, SortOrder::Count)
+            }
+            Category::Files => {
+                self.make_section(category, &collection.files, top_n, true, SortOrder::Count)
+            }
+            Category::Directories => self.make_section(
+                category,
+                &collection.directories,
+                top_n,
+                true,
+                self.options.dirs_sort,
+            ),
+            Category::Filenames => self.make_section(
+                category,
+                &collection.basenames,
+                top_n,
+                true,
+                SortOrder::Count,
+            ),
+            Category::Lines => {
+                let lines = scaled(count_warning_lines(&collection.warnings), scale);
+                self.make_section(category, &lines, top_n, true, SortOrder::Count)
+            }
+            Category::Keywords => self.make_section(
+                category,
+                &collection.keywords,
+                top_n,
+                true,
+                SortOrder::Count,
+            ),
+            Category::Hotspots => {
+                let hotspots = scaled(score_warning_hotspots(&collection.warnings), scale);
+                self.make_section(category, &hotspots, top_n, true, SortOrder::Count)
+            }
+            Category::Density => density_section(&collection.warnings, top_n),
+            Category::Tree => tree_section(&collection.warnings, scale),
+            Category::Scores => {
+                let weights = self.options.weights.clone().unwrap_or_default();
+                let scores = scaled(score_warning_files(&collection.warnings, &weights), scale);
+                self.make_section(category, &scores, top_n, false, SortOrder::Count)
+            }
+            Category::Themes => {
+                let keywords = self.make_section(
+                    category,
+                    &collection.keywords,
+                    top_n,
+                    true,
+                    SortOrder::Count,
+                );
+                themes_section(collection, keywords, scale, self.options.thousands_sep)
+            }
+            Category::Distribution => {
+                let buckets = self.options.distribution.clone().unwrap_or_default();
+                distribution_section(&collection.files, &buckets)
+            }
+            Category::Signatures => {
+                let signatures = scaled(count_warning_signatures(&collection.warnings), scale);
+                self.make_section(category, &signatures, top_n, true, SortOrder::Count)
+            }
+            Category::Linker => {
+                let linker = scaled(count_linker_warnings(&collection.warnings), scale);
+                self.make_section(category, &linker, top_n, false, SortOrder::Count)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.options.oneline {
+            return write!(f, "{}", self.collection.oneline());
+        }
+
+        if let Some(category) = self.options.only {
+            let section = self.section(category);
+            for line in &section.lines {
+                writeln!(f, "{line}")?;
+            }
+            if let (true, Some(total)) = (self.options.with_total, &section.total) {
+                writeln!(f, "{total}")?;
+            }
+            return Ok(());
+        }
+
+        let mut categories = self.options.sections.clone();
+        if self.options.hotspots {
+            categories.push(Category::Hotspots);
+        }
+        if self.options.density {
+            categories.push(Category::Density);
+        }
+        if self.options.tree {
+            categories.push(Category::Tree);
+        }
+        if self.options.weights.is_some() {
+            categories.push(Category::Scores);
+        }
+        if self.options.themes {
+            categories.push(Category::Themes);
+        }
+        if self.options.distribution.is_some() {
+            categories.push(Category::Distribution);
+        }
+        if self.options.signatures {
+            categories.push(Category::Signatures);
+        }
+        if self.options.linker {
+            categories.push(Category::Linker);
+        }
+        // Show sections in the order asked for, then the usual order,
+        // without duplicates
+        let position = |category: &Category| {
+            let order = &self.options.order;
+            order
+                .iter()
+                .position(|ordered| ordered == category)
+                .unwrap_or(order.len())
+        };
+        let mut categories = Category::ALL
+            .into_iter()
+            .filter(|category| categories.contains(category))
+            .collect::<Vec<_>>();
+        categories.sort_by_key(position);
+
+        if self.options.no_headers {
+            // Each section still ends with its total
+            if self.options.stats {
+                writeln!(f, "{}", self.collection.stats())?;
+            }
+            if let Some(weights) = &self.options.weights {
+                writeln!(
+                    f,
+                    "Weighted score: {}",
+                    self.collection.weighted_score(weights)
+                )?;
+            }
+            for category in categories {
+                let section = self.section(category).to_string();
+                if !section.is_empty() {
+                    writeln!(f, "{section}")?;
+                }
+            }
+            return Ok(());
+        }
+
+        let toolchains = self.collection.detected_toolchains();
+        if !toolchains.is_empty() {
+            writeln!(f, "Detected: {}\n", toolchains.join(", "))?;
+        }
+
+        if let Some(every) = self.collection.sample_every {
+            writeln!(
+                f,
+                "Estimated from 1 in {every} warnings, with counts scaled up to match\n"
+            )?;
+        }
+
+        if let Some(prefix) = self
+            .options
+            .relative_to
+            .as_ref()
+            .filter(|prefix| !prefix.as_os_str().is_empty())
+        {
+            let prefix = prefix.display().to_string();
+            let separator = if prefix.ends_with(std::path::MAIN_SEPARATOR) {
+                ""
+            } else {
+                std::path::MAIN_SEPARATOR_STR
+            };
+            writeln!(f, "Relative to: {prefix}{separator}\n")?;
+        }
+
+        if self.options.collapse_repeats {
+            let repeats = self.collection.warnings.len() - self.first_occurrences().len();
+            writeln!(
+                f,
+                "Counting each warning once per file for the Warnings and Files, leaving out {repeats} repeats\n"
+            )?;
+        }
+
+        if self.options.stats {
+            writeln!(f, "{}\n", self.collection.stats())?;
+        }
+
+        if let Some(weights) = &self.options.weights {
+            let score = self.collection.weighted_score(weights);
+            writeln!(f, "Weighted score: {score}\n")?;
+        }
+
+        if categories.contains(&Category::Signatures) {
+            let signatures = count_warning_signatures(&self.collection.warnings).len();
+            writeln!(f, "{signatures} distinct signatures.\n")?;
+        }
+
+        let body = categories
+            .iter()
+            .map(|&category| format!("{}:\n{}", category.title(), self.section(category)))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        writeln!(f, "{body}")
+    }
+}
+
+impl fmt::Display for WarningCollection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = ReportOptions {
+            top_n: f.precision().unwrap_or(10),
+            ..Default::default()
+        };
+        write!(f, "{}", self.report(&options))
+    }
+}
+
+impl std::str::FromStr for WarningCollection {
+    type Err = Error;
+
+    /// Parse warnings from a log with the default [`ParseOptions`]
+    ///
+    /// ```
+    /// # fn main() -> Result<(), warnsum::Error> {
+    /// let log = "\
+    /// src/main.c:10:5: warning: unused variable 'x' [-Wunused-variable]
+    /// src/util.c:3:1: warning: unused function 'helper' [-Wunused-function]
+    /// src/main.c:12:5: warning: unused variable 'y' [-Wunused-variable]
+    /// ";
+    /// let warnings: warnsum::WarningCollection = log.parse()?;
+    /// assert_eq!(warnings.total(), 3);
+    /// assert_eq!(warnings.names()["unused-variable"], 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        Ok(WarningCollection::with_options(
+            content,
+            &ParseOptions::default(),
+        ))
+    }
+}
+
+impl WarningCollection {
+    /// All the counts as CSV, with columns `category,key,count`
+    pub fn to_csv(&self) -> String {
+        self.to_delimited(b',')
+    }
+
+    /// As [`to_csv`](WarningCollection::to_csv), but with fields separated
+    /// by `delimiter`, such as `b'\t'` for TSV. Fields containing the
+    /// delimiter are quoted
+    pub fn to_delimited(&self, delimiter: u8) -> String {
+        let mut writer = csv_writer(delimiter);
+        write_csv_record(&mut writer, ["category", "key", "count"]);
+
+        let mut write_counts = |category: Category, counts: Vec<(String, i32)>| {
+            for (key, count) in counts {
+                write_csv_record(&mut writer, [category.to_string(), key, count.to_string()]);
+            }
+        };
+        write_counts(Category::Names, sorted_counts(&self.names));
+        write_counts(Category::Files, sorted_counts(&self.files));
+        write_counts(Category::Directories, sorted_counts(&self.directories));
+        write_counts(Category::Filenames, sorted_counts(&self.basenames));
+        write_counts(Category::Keywords, sorted_counts(&self.keywords));
+
+        finish_csv(writer)
+    }
+
+    /// All the counts as `category.key=count` lines, after a `total=` line.
+    /// Keys containing whitespace, `=`, `"`, or `\` are quoted, with any
+    /// `"` and `\` escaped by a `\`
+    pub fn to_key_values(&self) -> String {
+        let mut lines = vec![format!("total={}", self.total())];
+        let mut add_counts = |category: Category, counts: Vec<(String, i32)>| {
+            for (key, count) in counts {
+                lines.push(format!("{category}.{}={count}", quoted_key(&key)));
+            }
+        };
+        add_counts(Category::Names, sorted_counts(&self.names));
+        add_counts(Category::Files, sorted_counts(&self.files));
+        add_counts(Category::Directories, sorted_counts(&self.directories));
+        add_counts(Category::Filenames, sorted_counts(&self.basenames));
+        add_counts(Category::Keywords, sorted_counts(&self.keywords));
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    /// All the counts as gauges in Prometheus's text exposition format,
+    /// such as for the node exporter's textfile collector
+    pub fn to_prometheus(&self) -> String {
+        let mut result = String::new();
+        let mut add_metric = |metric: &str, help: &str, label: &str, counts: Vec<(String, i32)>| {
+            result.push_str(&format!("# HELP {metric} {help}\n# TYPE {metric} gauge\n"));
+            if label == "name" {
+                result.push_str(&format!("{metric} {}\n", self.total()));
+            }
+            for (key, count) in counts {
+                let value = escaped_label_value(&key);
+                result.push_str(&format!("{metric}{{{label}=\"{value}\"}} {count}\n"));
+            }
+        };
+        add_metric(
+            "warnsum_warnings_total",
+            "Number of compiler warnings, in total and by name",
+            "name",
+            sorted_counts(&self.names),
+        );
+        add_metric(
+            "warnsum_warnings_by_file",
+            "Number of compiler warnings in each file",
+            "file",
+            sorted_counts(&self.files),
+        );
+        add_metric(
+            "warnsum_warnings_by_directory",
+            "Number of compiler warnings in each directory",
+            "directory",
+            sorted_counts(&self.directories),
+        );
+        add_metric(
+            "warnsum_warnings_by_keyword",
+            "Number of compiler warnings with each keyword",
+            "keyword",
+            sorted_counts(&self.keywords),
+        );
+        result
+    }
+}
+
+/// `value` with backslashes, quotes, and newlines escaped, for a
+/// Prometheus label
+fn escaped_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `key` as it is, or in quotes if it would be ambiguous in a `key=value`
+/// line
+fn quoted_key(key: &str) -> String {
+    let needs_quotes = key
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '=' | '"' | '\\'));
+    if !needs_quotes {
+        return key.to_string();
+    }
+    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Counts as displayable strings, most common first, ties broken by key
+fn sorted_counts<T>(counts: &HashMap<T, i32>) -> Vec<(String, i32)>
+where
+    T: AsRef<Path> + Ord,
+{
+    let mut count_vec: Vec<_> = counts.iter().collect();
+    count_vec.sort_by(|lhs, rhs| rhs.1.cmp(lhs.1).then_with(|| lhs.0.cmp(rhs.0)));
+    count_vec
+        .into_iter()
+        .map(|(key, &count)| (key.as_ref().display().to_string(), count))
+        .collect()
+}
+
+/// Writer of CSV to memory, with fields separated by `delimiter`
+fn csv_writer(delimiter: u8) -> csv::Writer<Vec<u8>> {
+    csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new())
+}
+
+fn write_csv_record<I, T>(writer: &mut csv::Writer<Vec<u8>>, record: I)
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    writer
+        .write_record(record)
+        .expect("writing CSV to memory shouldn't fail");
+}
+
+fn finish_csv(writer: csv::Writer<Vec<u8>>) -> String {
+    let bytes = writer
+        .into_inner()
+        .expect("writing CSV to memory shouldn't fail");
+    String::from_utf8(bytes).expect("CSV should be built from valid UTF-8")
+}
+
+/// Statistics for a series of collections, such as daily snapshots
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trend {
+    snapshots: Vec<(String, Stats)>,
+}
+
+impl Trend {
+    pub fn new() -> Trend {
+        Trend::default()
+    }
+
+    /// Add the next collection in the series
+    pub fn push(&mut self, label: &str, collection: &WarningCollection) {
+        self.snapshots.push((label.to_string(), collection.stats()));
+    }
+
+    /// The trend as CSV, with one row per snapshot
+    pub fn to_csv(&self) -> String {
+        self.to_delimited(b',')
+    }
+
+    /// As [`to_csv`](Trend::to_csv), but with fields separated by
+    /// `delimiter`
+    pub fn to_delimited(&self, delimiter: u8) -> String {
+        let mut writer = csv_writer(delimiter);
+        write_csv_record(&mut writer, Trend::HEADINGS.map(str::to_lowercase));
+        for (label, stats) in &self.snapshots {
+            write_csv_record(&mut writer, Trend::row(label, stats));
+        }
+        finish_csv(writer)
+    }
+
+    const HEADINGS: [&'static str; 6] = [
+        "Snapshot",
+        "Warnings",
+        "Names",
+        "Files",
+        "Directories",
+        "Keywords",
+    ];
+
+    fn row(label: &str, stats: &Stats) -> [String; 6] {
+        [
+            label.to_string(),
+            stats.warnings.to_string(),
+            stats.names.to_string(),
+            stats.files.to_string(),
+            stats.directories.to_string(),
+            stats.keywords.to_string(),
+        ]
+    }
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self
+            .snapshots
+            .iter()
+            .map(|(label, stats)| Trend::row(label, stats))
+            .collect::<Vec<_>>();
+
+        let mut widths = Trend::HEADINGS.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let headings = Trend::HEADINGS.map(str::to_string);
+        for row in std::iter::once(&headings).chain(&rows) {
+            let mut cells = row.iter().zip(widths);
+            let (label, label_width) = cells.next().expect("rows shouldn't be empty");
+            let mut line = format!("{label:<label_width$}");
+            for (cell, width) in cells {
+                line += &format!("  {cell:>width$}");
+            }
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How many distinct keywords there are of each length, and how often they
+/// come up, to help pick [`ParseOptions::keyword_len`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeywordLengths(BTreeMap<usize, (usize, i32)>);
+
+impl KeywordLengths {
+    const HEADINGS: [&'static str; 3] = ["Length", "Keywords", "Occurrences"];
+
+    pub fn new(collection: &WarningCollection) -> KeywordLengths {
+        let mut lengths: BTreeMap<usize, (usize, i32)> = BTreeMap::new();
+        for (keyword, &count) in &collection.keywords {
+            let (distinct, total) = lengths.entry(keyword.chars().count()).or_default();
+            *distinct += 1;
+            *total = total.saturating_add(count);
+        }
+        KeywordLengths(lengths)
+    }
+
+    /// Number of distinct keywords of `length` characters, and their total
+    /// number of occurrences
+    pub fn get(&self, length: usize) -> Option<(usize, i32)> {
+        self.0.get(&length).copied()
+    }
+}
+
+impl fmt::Display for KeywordLengths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [length_width, distinct_width, total_width] = KeywordLengths::HEADINGS.map(str::len);
+        writeln!(f, "{}", KeywordLengths::HEADINGS.join("  "))?;
+        for (length, (distinct, total)) in &self.0 {
+            writeln!(
+                f,
+                "{length:>length_width$}  {distinct:>distinct_width$}  {total:>total_width$}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Index from warning names to the positions of those warnings in a
+/// collection, for answering many queries about single names
+#[derive(Debug, Clone)]
+pub struct NameIndex<'a> {
+    warnings: &'a [Warning],
+    indices: HashMap<&'a str, Vec<usize>>,
+}
+
+impl<'a> NameIndex<'a> {
+    pub fn new(collection: &'a WarningCollection) -> NameIndex<'a> {
+        let mut indices: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, warning) in collection.warnings.iter().enumerate() {
+            indices.entry(&warning.name).or_default().push(index);
+        }
+        NameIndex {
+            warnings: &collection.warnings,
+            indices,
+        }
+    }
+
+    /// Positions of the warnings called `name`, in the order they were found
+    pub fn indices(&self, name: &str) -> &[usize] {
+        self.indices.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The warnings called `name`, in the order they were found
+    pub fn warnings(&self, name: &str) -> impl Iterator<Item = &'a Warning> + '_ {
+        self.indices(name)
+            .iter()
+            .map(|&index| &self.warnings[index])
+    }
+
+    /// Counts of the keywords in the warnings called `name`
+    pub fn keywords_for(&self, name: &str, fold_case: bool) -> HashMap<String, i32> {
+        let warnings = self.warnings(name).cloned().collect::<Vec<_>>();
+        count_warning_keywords(&warnings, fold_case)
+    }
+}
+
+/// How often the most common keywords appear in the most common warnings
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crosstab {
+    names: Vec<String>,
+    keywords: Vec<String>,
+    /// One row per name, with one count per keyword
+    counts: Vec<Vec<i32>>,
+}
+
+impl Crosstab {
+    /// Cross-tabulate the `top_names` most common warning names against the
+    /// `top_keywords` most common keywords, all of them if zero
+    pub fn new(collection: &WarningCollection, top_names: usize, top_keywords: usize) -> Crosstab {
+        let top = |counts: Vec<(String, i32)>, n: usize| {
+            let n = if n == 0 { counts.len() } else { n };
+            counts
+                .into_iter()
+                .take(n)
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>()
+        };
+        let names = top(sorted_counts(&collection.names), top_names);
+        let keywords = top(sorted_counts(&collection.keywords), top_keywords);
+
+        let index = NameIndex::new(collection);
+        let counts = names
+            .iter()
+            .map(|name| {
+                let counts = index.keywords_for(name, collection.fold_keyword_case);
+                keywords
+                    .iter()
+                    .map(|keyword| counts.get(keyword).copied().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+
+        Crosstab {
+            names,
+            keywords,
+            counts,
+        }
+    }
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let headings = std::iter::once("Warning".to_string()).chain(self.keywords.iter().cloned());
+        std::iter::once(headings.collect())
+            .chain(self.names.iter().zip(&self.counts).map(|(name, counts)| {
+                std::iter::once(name.clone())
+                    .chain(counts.iter().map(i32::to_string))
+                    .collect()
+            }))
+            .collect()
+    }
+
+    /// The cross-tabulation as CSV, with one row per warning name
+    pub fn to_csv(&self) -> String {
+        self.to_delimited(b',')
+    }
+
+    /// As [`to_csv`](Crosstab::to_csv), but with fields separated by
+    /// `delimiter`
+    pub fn to_delimited(&self, delimiter: u8) -> String {
+        let mut writer = csv_writer(delimiter);
+        for row in self.rows() {
+            write_csv_record(&mut writer, row);
+        }
+        finish_csv(writer)
+    }
+}
+
+impl fmt::Display for Crosstab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.rows();
+        let mut widths = vec![0; self.keywords.len() + 1];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        for row in &rows {
+            let mut cells = row.iter().zip(&widths);
+            let (name, name_width) = cells.next().expect("rows shouldn't be empty");
+            let mut line = format!("{name:<name_width$}");
+            for (cell, width) in cells {
+                line += &format!("  {cell:>width$}");
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Length of the `{bar}` of the item with the highest count in a section
+const BAR_WIDTH: usize = 20;
+
+/// Tokens that can be used in a [`LineTemplate`], in the order their values
+/// are given to [`LineTemplate::render`]
+const TEMPLATE_TOKENS: [&str; 5] = ["count", "key", "percent", "rank", "bar"];
+
+/// Format for each line of the sections of a summary, such as
+/// `{count} {key} ({percent}%)`. The tokens are:
+///
+/// - `{count}`: the count of the item
+/// - `{key}`: the item itself, such as a warning name or file
+/// - `{percent}`: the count as a percentage of the section's total
+/// - `{rank}`: the 1-based position of the item in the section
+/// - `{bar}`: a bar of `#`s as long as the count, relative to the highest
+///
+/// ```
+/// # use warnsum::LineTemplate;
+/// assert!("{rank}. {key}: {count}".parse::<LineTemplate>().is_ok());
+/// assert!("{name}".parse::<LineTemplate>().is_err());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineTemplate(Vec<TemplatePart>);
+
+#[derive(Debug, PartialEq, Clone)]
+enum TemplatePart {
+    Text(String),
+    /// Index into [`TEMPLATE_TOKENS`]
+    Token(usize),
+}
+
+impl LineTemplate {
+    /// The template with each token replaced by its value in `values`,
+    /// given in the order of [`TEMPLATE_TOKENS`]
+    fn render(&self, values: &[String; TEMPLATE_TOKENS.len()]) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => text.as_str(),
+                TemplatePart::Token(index) => values[*index].as_str(),
+            })
+            .collect()
+    }
+}
+
+impl std::str::FromStr for LineTemplate {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find('}') else {
+                return Err(Error::InvalidToken(rest[start..].to_string()));
+            };
+            let token = &rest[start + 1..start + end];
+            let index = TEMPLATE_TOKENS
+                .iter()
+                .position(|known| *known == token)
+                .ok_or_else(|| Error::InvalidToken(format!("{{{token}}}")))?;
+            parts.push(TemplatePart::Token(index));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        Ok(LineTemplate(parts))
+    }
+}
+
+/// The rendered lines of one section of a summary
+#[derive(Debug, Default)]
+struct Section {
+    /// One line per item
+    lines: Vec<String>,
+
+    /// Note of how many items were left out
+    extra: Option<String>,
+
+    /// Line with the total for the section
+    total: Option<String>,
+
+    /// Width of the columns before the item on each line
+    indent: usize,
+
+    /// Sum of the counts of all the items, including any left out, which
+    /// percentages are of
+    sum: i32,
+
+    /// The item and count shown on each line
+    items: Vec<(String, i32)>,
+}
+
+impl Section {
+    /// Prefix each line with its 1-based position in the section, keeping
+    /// the count column aligned
+    fn ranked(self) -> Section {
+        let width = self.lines.len().to_string().len() + 1;
+        let pad = |line: String| format!("{:width$}  {line}", "");
+
+        Section {
+            lines: self
+                .lines
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| format!("{:<width$}  {line}", format!("#{}", index + 1)))
+                .collect(),
+            extra: self.extra.map(pad),
+            total: self.total.map(pad),
+            indent: self.indent + width + 2,
+            sum: self.sum,
+            items: self.items,
+        }
+    }
+
+    /// Replace each line with `template` filled in from its item, grouping
+    /// the thousands of counts with `separator`, if any. Templated lines
+    /// have no columns before the item, so truncating shortens the whole
+    /// line
+    fn templated(self, template: &LineTemplate, separator: Option<char>) -> Section {
+        let max = self.items.iter().map(|(_, count)| *count).max();
+        let lines = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, (key, count))| {
+                let percent = if self.sum > 0 {
+                    f64::from(*count) * 100.0 / f64::from(self.sum)
+                } else {
+                    0.0
+                };
+                let bar = match max {
+                    Some(max) if max > 0 && *count > 0 => {
+                        let length = (i64::from(*count) * BAR_WIDTH as i64 / i64::from(max)).max(1);
+                        "#".repeat(length as usize)
+                    }
+                    _ => String::new(),
+                };
+                template.render(&[
+                    grouped_count(*count, separator),
+                    key.clone(),
+                    round_average(percent),
+                    (index + 1).to_string(),
+                    bar,
+                ])
+            })
+            .collect();
+        Section {
+            lines,
+            indent: 0,
+            ..self
+        }
+    }
+
+    /// Shorten the item on each line by replacing its middle with an
+    /// ellipsis, so that no line is wider than `max_width`
+    fn truncated(self, max_width: usize) -> Section {
+        let item_width = max_width.saturating_sub(self.indent);
+        Section {
+            lines: self
+                .lines
+                .into_iter()
+                .map(|line| {
+                    let (columns, item) = line.split_at(self.indent.min(line.len()));
+                    format!("{columns}{}", truncate_middle(item, item_width))
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Follow each line with how far its count is from the historical
+    /// average for its item, or that it's new
+    fn versus_averages(self, averages: &HashMap<String, f64>) -> Section {
+        let lines = self
+            .lines
+            .into_iter()
+            .zip(&self.items)
+            .map(|(line, (item, count))| match averages.get(item) {
+                Some(&average) => {
+                    let deviation = round_average(f64::from(*count) - average);
+                    let sign = if deviation.starts_with('-') { "" } else { "+" };
+                    let average = round_average(average);
+                    format!("{line}  ({sign}{deviation} vs avg {average})")
+                }
+                None => format!("{line}  (new)"),
+            })
+            .collect();
+        Section { lines, ..self }
+    }
+}
+
+/// An average to one decimal place, without the decimal if it's whole
+fn round_average(average: f64) -> String {
+    // Adding zero turns -0 into 0
+    let rounded = (average * 10.0).round() / 10.0 + 0.0;
+    if rounded.fract() == 0.0 {
+        format!("{rounded:.0}")
+    } else {
+        format!("{rounded:.1}")
+    }
+}
+
+/// Replace the middle of `text` with "..." so that it is at most `width`
+/// characters long, keeping more of the end, where the basename of a path is
+fn truncate_middle(text: &str, width: usize) -> String {
+    const ELLIPSIS: &str = "...";
+
+    let length = text.chars().count();
+    if length <= width {
+        return text.to_string();
+    }
+    if width <= ELLIPSIS.len() {
+        return ELLIPSIS[..width].to_string();
+    }
+
+    let available = width - ELLIPSIS.len();
+    let head = available / 3;
+    let tail = available - head;
+    let start = text.chars().take(head);
+    let end = text.chars().skip(length - tail);
+    start.chain(ELLIPSIS.chars()).chain(end).collect()
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self
+            .lines
+            .iter()
+            .chain(&self.extra)
+            .chain(&self.total)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+fn make_section<T>(
+    warnings: &HashMap<T, i32>,
+    top_n: usize,
+    use_total_items: bool,
+    sort: SortOrder,
+) -> Section
+where
+    T: AsRef<Path> + Eq + Ord,
+{
+    make_section_by(warnings, top_n, use_total_items, sort, None, None)
+}
+
+/// `count`, with its thousands separated by `separator`, if any
+fn grouped_count(count: i32, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return count.to_string();
+    };
+    let digits = count.unsigned_abs().to_string();
+    let mut result = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            result.push(separator);
+        }
+        result.push(digit);
+    }
+    if count < 0 {
+        result.insert(0, '-');
+    }
+    result
+}
+
+/// As [`make_section`], but with ties in count broken by when the items
+/// were first seen, if `first_seen` gives that for each displayed item, and
+/// counts grouped by `separator`
+fn make_section_by<T>(
+    warnings: &HashMap<T, i32>,
+    top_n: usize,
+    use_total_items: bool,
+    sort: SortOrder,
+    first_seen: Option<&HashMap<String, usize>>,
+    separator: Option<char>,
+) -> Section
+where
+    T: AsRef<Path> + Eq + Ord,
+{
+    if warnings.is_empty() {
+        return Section::default();
+    }
+
+    let seen = |key: &T| {
+        first_seen.map_or(0, |first_seen| {
+            let key = key.as_ref().display().to_string();
+            first_seen.get(&key).copied().unwrap_or(usize::MAX)
+        })
+    };
+    let mut count_vec: Vec<_> = warnings.iter().collect();
+    count_vec.sort_by(|lhs, rhs| match sort {
+        SortOrder::Count => {
+            if lhs.1 == rhs.1 {
+                seen(lhs.0).cmp(&seen(rhs.0)).then_with(|| lhs.0.cmp(rhs.0))
+            } else {
+                lhs.1.cmp(rhs.1).reverse()
+            }
+        }
+        SortOrder::Key => lhs.0.cmp(rhs.0),
+    });
+
+    let max_length = if top_n == 0 {
+        count_vec.len()
+    } else {
+        std::cmp::min(count_vec.len(), top_n)
+    };
+
+    // Sums of zero or less are possible with filtered or diffed counts
+    let sum = warnings.values().sum::<i32>();
+    let min_width = if sum > 0 {
+        grouped_count(sum, separator).chars().count()
+    } else {
+        1
+    };
+
+    let items = count_vec
+        .iter()
+        .take(max_length)
+        .map(|(key, &count)| (key.as_ref().display().to_string(), count))
+        .collect::<Vec<_>>();
+    let lines = items
+        .iter()
+        .map(|(key, count)| format!("{:>min_width$}  {key}", grouped_count(*count, separator)))
+        .collect();
+    let extra = if count_vec.len() > top_n && top_n != 0 {
+        Some(format!(
+            "{1:0$}  (+{2} more items)",
+            min_width,
+            " ",
+            count_vec.len() - top_n
+        ))
+    } else {
+        None
+    };
+
+    let total: i32 = if use_total_items {
+        count_vec.len() as i32
+    } else {
+        warnings.values().sum()
+    };
+    let total = Some(format!(
+        "{:>min_width$}  Total",
+        grouped_count(total, separator)
+    ));
+
+    Section {
+        lines,
+        extra,
+        total,
+        indent: min_width + 2,
+        sum,
+        items,
+    }
+}
+
+// Helper function from https://stackoverflow.com/a/45145246
+#[cfg(test)]
+macro_rules! vec_of_strings {
     // match a list of expressions separated by comma:
     ($($str:expr),*) => ({
         // create a Vec with this list of expressions,
@@ -293,194 +4867,3088 @@ macro_rules! vec_of_strings {
     });
 }
 
-#[cfg(test)]
-lazy_static! {
-    static ref TEST_WARNINGS: WarningCollection = WarningCollection {
-        warnings: Vec::from([
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir1/file1.c"),
-                name: String::from("bad-thing"),
-                keywords: vec_of_strings!["horrible", "zing", "zimb"],
-            },
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir2/file1.c"),
-                name: String::from("dont-like-this"),
-                keywords: vec_of_strings!["zing", "zimb", "zang"],
-            },
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir2/file2.c"),
-                name: String::from("horrible-stuff"),
-                keywords: vec_of_strings!["horrible", "stuff"],
-            },
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir2/file2.c"),
-                name: String::from("horrible-stuff"),
-                keywords: vec_of_strings!["horrible", "stuff"],
-            },
-        ]),
-        names: HashMap::from([
-            ("bad-thing".to_string(), 1),
-            ("dont-like-this".to_string(), 1),
-            ("horrible-stuff".to_string(), 2),
-        ]),
-        files: HashMap::from([
-            (PathBuf::from("/path/to/dir1/file1.c"), 1),
-            (PathBuf::from("/path/to/dir2/file1.c"), 1),
-            (PathBuf::from("/path/to/dir2/file2.c"), 2),
-        ]),
-        directories: HashMap::from([
-            (PathBuf::from("/path/to/dir1"), 1),
-            (PathBuf::from("/path/to/dir2"), 3)
-        ]),
-        keywords: HashMap::from([
-            ("horrible".to_string(), 3),
-            ("stuff".to_string(), 2),
-            ("zang".to_string(), 1),
-            ("zimb".to_string(), 2),
-            ("zing".to_string(), 2),
-        ])
+#[cfg(test)]
+lazy_static! {
+    static ref TEST_WARNINGS: WarningCollection = WarningCollection {
+        warnings: Vec::from([
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir1/file1.c"),
+                line: Some(235),
+                column: Some(36),
+                name: String::from("bad-thing"),
+                keywords: vec_of_strings!["horrible", "zing", "zimb"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir2/file1.c"),
+                line: Some(340),
+                column: Some(27),
+                name: String::from("dont-like-this"),
+                keywords: vec_of_strings!["zing", "zimb", "zang"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir2/file2.c"),
+                line: Some(697),
+                column: Some(16),
+                name: String::from("horrible-stuff"),
+                keywords: vec_of_strings!["horrible", "stuff"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir2/file2.c"),
+                line: Some(715),
+                column: Some(18),
+                name: String::from("horrible-stuff"),
+                keywords: vec_of_strings!["horrible", "stuff"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+        ]),
+        names: HashMap::from([
+            ("bad-thing".to_string(), 1),
+            ("dont-like-this".to_string(), 1),
+            ("horrible-stuff".to_string(), 2),
+        ]),
+        files: HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.c"), 1),
+            (PathBuf::from("/path/to/dir2/file1.c"), 1),
+            (PathBuf::from("/path/to/dir2/file2.c"), 2),
+        ]),
+        directories: HashMap::from([
+            (PathBuf::from("/path/to/dir1"), 1),
+            (PathBuf::from("/path/to/dir2"), 3)
+        ]),
+        basenames: HashMap::from([(PathBuf::from("file1.c"), 2), (PathBuf::from("file2.c"), 2),]),
+        keywords: HashMap::from([
+            ("horrible".to_string(), 3),
+            ("stuff".to_string(), 2),
+            ("zang".to_string(), 1),
+            ("zimb".to_string(), 2),
+            ("zing".to_string(), 2),
+        ]),
+        fold_keyword_case: false,
+        truncated: false,
+        sample_every: None,
+        profile: None,
+    };
+}
+
+#[test]
+fn find_a_warning() {
+    let result = WarningCollection::new(
+        "Some warnings
+[  1%] Generating file1.c
+[  2%] Generating file2.c
+/path/to/dir1/file1.c: In function ‘func1’:
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |     if (horrible) *foo = zing->zimb;
+      |                                ^~~~~
+/path/to/dir2/file1.c: In function ‘func2’:
+/path/to/dir2/file1.c:340:27: warning: don't like this [-Wdont-like-this]
+  340 |     zing->zimb &= (~foo.zang);
+      |                     ^~
+/path/to/dir2/file2.c: In function ‘func3’:
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+  697 |     horrible = stuff;
+      |                ^~~
+/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+  715 |       horrible = stuff[i];
+      |                  ^~~
+",
+        3,
+        &["foo"],
+    );
+
+    assert_eq!(result, *TEST_WARNINGS);
+    assert_eq!(result.names, TEST_WARNINGS.names);
+    assert_eq!(result.files, TEST_WARNINGS.files);
+    assert_eq!(result.directories, TEST_WARNINGS.directories);
+    assert_eq!(result.keywords, TEST_WARNINGS.keywords);
+}
+
+#[cfg(test)]
+const TEST_FORTRAN_LOG: &str = "Some warnings
+[  1%] Generating file1.c
+[  2%] Generating file2.c
+/path/to/dir1/file1.c:235:36:
+
+  235 |     if (horrible) *foo = zing->zimb;
+      |                                1
+Warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:340:27:
+
+  340 |     zing->zimb &= (~foo.zang);
+      |                     ^
+Warning: don't like this [-Wdont-like-this]
+/path/to/dir2/file2.c:697:16:
+
+  697 |     horrible = stuff;
+      |                ^~~
+Warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:715:18:
+
+  715 |       horrible = stuff[i];
+      |                  ^~~
+Warning: just horrible stuff [-Whorrible-stuff]
+";
+
+#[test]
+fn find_a_warning_fortran() {
+    let result = WarningCollection::new(TEST_FORTRAN_LOG, 3, &["foo"]);
+
+    let expected = WarningCollection {
+        warnings: TEST_WARNINGS
+            .warnings
+            .iter()
+            .map(|warning| Warning {
+                toolchain: String::from("gfortran"),
+                ..warning.clone()
+            })
+            .collect(),
+        ..TEST_WARNINGS.clone()
+    };
+    assert_eq!(result, expected);
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["gfortran"]);
+    assert_eq!(result.names, TEST_WARNINGS.names);
+    assert_eq!(result.files, TEST_WARNINGS.files);
+    assert_eq!(result.directories, TEST_WARNINGS.directories);
+    assert_eq!(result.keywords, TEST_WARNINGS.keywords);
+}
+
+#[test]
+fn format_hash_map_for_warnings() {
+    let counts = HashMap::from([
+        ("result1".to_string(), 3),
+        ("result2".to_string(), 120),
+        ("result3".to_string(), 1),
+    ]);
+
+    let result = make_section(&counts, 2, false, SortOrder::Count).to_string();
+    let expected = "120  result2\n  3  result1\n     (+1 more items)\n124  Total".to_string();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn format_hash_map_for_files() {
+    let counts = HashMap::from([
+        ("result1".to_string(), 3),
+        ("result2".to_string(), 120),
+        ("result3".to_string(), 1),
+    ]);
+
+    let result = make_section(&counts, 2, true, SortOrder::Count).to_string();
+    let expected = "120  result2\n  3  result1\n     (+1 more items)\n  3  Total".to_string();
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn aggregate_only_counts() {
+    let chunk = "\
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |     if (bad_thing) *foo = zing->bat.pazz.zimb;
+      |                                    ^~~~~
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+  697 |     horrible = stuff;
+      |                ^~~
+/path/to/dir2/file3.f90:12:9: warning: Unused variable 'zing' declared at (1) [-Wunused-variable]
+Warning: /path/to/dir3/file4.f90, line 7: Unused dummy variable ZANG
+";
+    // Big enough to be split into several chunks
+    let log = chunk.repeat(400);
+    let aggregate = WarningCollection::aggregate_only(&log, &ParseOptions::default());
+    let full = WarningCollection::new(&log, 5, &[] as &[String]);
+    assert!(aggregate.warnings.is_empty());
+    assert_eq!(aggregate.names, full.names);
+    assert_eq!(aggregate.files, full.files);
+    assert_eq!(aggregate.directories, full.directories);
+    assert_eq!(aggregate.basenames, full.basenames);
+    assert_eq!(aggregate.keywords, full.keywords);
+    assert_eq!(aggregate.total(), 1600);
+    assert_eq!(aggregate.diff(&full), full.diff(&full));
+
+    let options = ParseOptions {
+        sample_every: Some(3),
+        ..Default::default()
+    };
+    let aggregate = WarningCollection::aggregate_only(&log, &options);
+    let full = WarningCollection::with_options(&log, &options);
+    assert_eq!(aggregate.total(), full.total());
+    assert_eq!(aggregate.sample_every(), Some(3));
+}
+
+#[test]
+fn aggregate_more_than_i16_warnings() {
+    let log = "src/a.c:1:1: warning: bad [-Wbad]\n".repeat(33_000);
+    let aggregate = WarningCollection::aggregate_only(&log, &ParseOptions::default());
+    assert_eq!(aggregate.total(), 33_000);
+    assert_eq!(aggregate.names["bad"], 33_000);
+    assert_eq!(aggregate.files[&PathBuf::from("src/a.c")], 33_000);
+}
+
+#[test]
+fn warning_diff() {
+    let new_warnings = WarningCollection {
+        warnings: Vec::from([
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir1/file1.c"),
+                line: Some(235),
+                column: Some(36),
+                name: String::from("bad-thing"),
+                keywords: vec_of_strings!["horrible", "zing", "zimb"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+            Warning {
+                file: std::path::PathBuf::from("/path/to/dir2/file1.c"),
+                line: Some(340),
+                column: Some(27),
+                name: String::from("dont-like-this"),
+                keywords: vec_of_strings!["zing", "zimb", "zang"],
+                toolchain: String::from("gcc"),
+                raw: None,
+                message: None,
+            },
+        ]),
+        names: HashMap::from([
+            ("bad-thing".to_string(), 1),
+            ("dont-like-this".to_string(), 1),
+        ]),
+        files: HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.c"), 1),
+            (PathBuf::from("/path/to/dir2/file1.c"), 1),
+        ]),
+        directories: HashMap::from([
+            (PathBuf::from("/path/to/dir1"), 1),
+            (PathBuf::from("/path/to/dir2"), 1),
+        ]),
+        basenames: HashMap::from([(PathBuf::from("file1.c"), 2)]),
+        keywords: HashMap::from([
+            ("horrible".to_string(), 1),
+            ("zang".to_string(), 1),
+            ("zimb".to_string(), 2),
+            ("zing".to_string(), 2),
+        ]),
+        fold_keyword_case: false,
+        truncated: false,
+        sample_every: None,
+        profile: None,
+    };
+    let result = new_warnings.diff(&TEST_WARNINGS);
+
+    let expected = WarningCollectionDiff {
+        names: HashMap::from([("horrible-stuff".to_string(), -2)]),
+        files: HashMap::from([(PathBuf::from("/path/to/dir2/file2.c"), -2)]),
+        directories: HashMap::from([(PathBuf::from("/path/to/dir2"), -2)]),
+        basenames: HashMap::from([(PathBuf::from("file2.c"), -2)]),
+        keywords: HashMap::from([("horrible".to_string(), -2), ("stuff".to_string(), -2)]),
+    };
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn changed_files_of_diff() {
+    let mut current = TEST_WARNINGS.clone();
+    current
+        .warnings
+        .retain(|warning| warning.file != Path::new("/path/to/dir2/file1.c"));
+    current.warnings.push(Warning {
+        file: PathBuf::from("/path/to/dir3/file3.c"),
+        line: Some(12),
+        column: Some(3),
+        name: String::from("bad-thing"),
+        keywords: vec_of_strings!["bad", "thing"],
+        toolchain: String::from("gcc"),
+        raw: None,
+        message: None,
+    });
+    current.recount();
+
+    // Files with the same number of warnings aren't listed
+    assert_eq!(
+        current.diff(&TEST_WARNINGS).changed_files(),
+        [
+            (Path::new("/path/to/dir2/file1.c"), -1),
+            (Path::new("/path/to/dir3/file3.c"), 1),
+        ]
+    );
+    assert!(TEST_WARNINGS
+        .diff(&TEST_WARNINGS)
+        .changed_files()
+        .is_empty());
+}
+
+#[test]
+fn find_a_warning_custom_regex() {
+    let options = ParseOptions {
+        keyword_len: 3,
+        custom_parsers: vec![CustomParser::new(
+            r"(?m)^MYTOOL (?P<file>\S+)\((?P<line>\d+)\): (?P<name>W\d+) (?P<text>.*)$",
+        )
+        .unwrap()],
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(
+        "Running mytool
+MYTOOL src/file1.c(12): W101 horrible stuff here
+MYTOOL src/file2.c(40): W101 zing zimb
+MYTOOL src/file2.c(41): W202 zang
+",
+        &options,
+    );
+
+    assert_eq!(
+        result.names,
+        HashMap::from([("W101".to_string(), 2), ("W202".to_string(), 1)])
+    );
+    assert_eq!(
+        result.files,
+        HashMap::from([
+            (PathBuf::from("src/file1.c"), 1),
+            (PathBuf::from("src/file2.c"), 2),
+        ])
+    );
+    assert_eq!(result.warnings[0].line, Some(12));
+    assert_eq!(result.warnings[0].column, None);
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["horrible", "stuff", "here"]
+    );
+}
+
+#[test]
+fn profile_stages() {
+    let log = "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |   horrible(stuff);
+      |   ^~~~~~~~
+";
+    assert_eq!(
+        WarningCollection::new(log, 5, &[] as &[String]).profile(),
+        None
+    );
+
+    let options = ParseOptions {
+        profile: true,
+        ..Default::default()
+    };
+    let profile = WarningCollection::with_options(log, &options)
+        .profile()
+        .unwrap();
+    assert!(profile.parse > Duration::ZERO);
+    assert!(profile.keywords > Duration::ZERO);
+    assert_eq!(profile.render, Duration::ZERO);
+
+    let table = profile.to_string();
+    let stages = table
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(stages, ["parse", "keywords", "count", "render", "total"]);
+}
+
+#[test]
+fn annotate_source() {
+    let source = "int main() {
+  int x;
+  return 0;
+}
+";
+    let log = "main.c:2:7: warning: unused variable 'x' [-Wunused-variable]
+main.c:9:1: warning: past the end [-Wpast]
+other.c:2:1: warning: elsewhere [-Welsewhere]
+";
+    let result = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(
+        result.annotate(Path::new("main.c"), source),
+        "int main() {
+  int x;
+>>> 2:7: warning unused-variable
+  return 0;
+}
+>>> 9:1: warning past
+"
+    );
+}
+
+#[test]
+fn keywords_from_notes() {
+    let log = "/path/to/file1.c:12:3: warning: comparison of integers [-Wsign-compare]
+/path/to/file1.c:4:10: note: declared here as 'unsigned_counter'
+/path/to/file1.c:7:9: note: #pragma message: TODO: pragmatic
+/path/to/file2.c:20:3: warning: unused variable [-Wunused-variable]
+";
+    let result = WarningCollection::new(log, 5, &[] as &[String]);
+    assert!(!result.keywords.contains_key("unsigned_counter"));
+
+    let options = ParseOptions {
+        include_notes_keywords: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.keywords["unsigned_counter"], 1);
+    assert_eq!(result.keywords["declared"], 1);
+    assert!(!result.keywords.contains_key("pragmatic"));
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["declared", "unsigned_counter"]
+    );
+    assert!(result.warnings[1].keywords.is_empty());
+    assert_eq!(result.total(), 2);
+
+    // A note in the next chunk still goes with the warning before it
+    let mut ingest = WarningCollection::ingest(&options);
+    for line in log.split_inclusive('\n') {
+        ingest.push(line);
+    }
+    assert_eq!(ingest.finish(), result);
+}
+
+#[test]
+fn report_relative_to_common_prefix() {
+    let log = "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let mut collection = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(collection.strip_common_prefix(), PathBuf::from("/path/to"));
+    assert_eq!(collection.files[&PathBuf::from("dir2/file2.c")], 2);
+
+    let options = ReportOptions {
+        relative_to: Some(PathBuf::from("/path/to")),
+        ..Default::default()
+    };
+    let report = collection.report(&options).to_string();
+    assert!(report.starts_with("Detected: gcc\n\nRelative to: /path/to/\n\nWarnings:"));
+    assert!(report.contains("2  dir2/file2.c\n"));
+    assert!(!report.contains("/path/to/dir2"));
+}
+
+#[test]
+fn first_and_last_lines() {
+    let log = "Building
+/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |   horrible(stuff);
+      |   ^~~~~~~~
+/path/to/file2.c:340:27: warning: don't like this [-Wdont-like-this]
+  340 |   zing(zimb);
+      |   ^~~~
+Done
+";
+    assert_eq!(first_lines(log, 0), "");
+    assert_eq!(first_lines(log, 1), "Building\n");
+    // The rest of the first warning comes with it
+    assert_eq!(
+        first_lines(log, 2),
+        &log[..log.find("/path/to/file2.c").unwrap()]
+    );
+    assert_eq!(first_lines(log, 100), log);
+
+    assert_eq!(last_lines(log, 0), "");
+    // Back to the start of the second warning, which may not have ended
+    let second = &log[log.find("/path/to/file2.c").unwrap()..];
+    assert_eq!(last_lines(log, 1), second);
+    assert_eq!(last_lines(log, 4), second);
+    assert_eq!(
+        last_lines(log, 5),
+        &log[log.find("/path/to/file1.c").unwrap()..]
+    );
+    assert_eq!(last_lines(log, 100), log);
+
+    let tail = WarningCollection::new(last_lines(log, 3), 4, &[] as &[String]);
+    assert_eq!(
+        tail.names,
+        HashMap::from([("dont-like-this".to_string(), 1)])
+    );
+    assert_eq!(tail.warnings[0].keywords, vec_of_strings!["zing", "zimb"]);
+}
+
+#[test]
+fn read_xcodebuild_warnings() {
+    let log = r#"CompileSwift normal arm64 /Users/dev/App/Sources/Model.swift
+/Users/dev/App/Sources/Model.swift:12:9: warning: variable 'count' was never mutated; consider changing to 'let' constant
+/Users/dev/App/Sources/Model.swift:30:13: warning: initialization of immutable value 'result' was never used
+/Users/dev/App/Sources/View.swift:8:5: warning: variable 'total' was never mutated; consider changing to 'let' constant
+CompileC /Users/dev/App/Legacy/Parser.m normal arm64
+/Users/dev/App/Legacy/Parser.m:44:17: warning: incompatible pointer types assigning to 'NSString *' from 'NSNumber *' [-Wincompatible-pointer-types]
+/Users/dev/App/Legacy/Parser.m:50:3: warning: 'stringByAddingPercentEscapesUsingEncoding:' is deprecated [-Wdeprecated-declarations]
+"#;
+    let result = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("variable-was-never".to_string(), 2),
+            ("initialization-of-immutable".to_string(), 1),
+            ("incompatible-pointer-types".to_string(), 1),
+            ("deprecated-declarations".to_string(), 1),
+        ])
+    );
+    assert_eq!(result.detected_toolchains(), ["swift", "gcc"]);
+    assert_eq!(result.keywords["count"], 1);
+
+    let options = ParseOptions {
+        message_name_words: 1,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.names["variable"], 2);
+}
+
+#[test]
+fn strip_build_system_prefixes() {
+    let log = "\
+INFO: /path/to/file1.c:12:3: warning: unused variable 'x' [-Wunused-variable]
+[1,234 / 5,678] /path/to/file2.c:4:1: warning: unused function 'f' [-Wunused-function]
+[12/345] /path/to/file3.f90:7:9: warning: Unused dummy argument 'y' [-Wunused-dummy-argument]
+INFO: Elapsed time: 12.3s
+";
+    let result = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(
+        result.files,
+        HashMap::from([
+            (PathBuf::from("/path/to/file1.c"), 1),
+            (PathBuf::from("/path/to/file2.c"), 1),
+            (PathBuf::from("/path/to/file3.f90"), 1),
+        ])
+    );
+
+    let options = ParseOptions::default();
+    let mut ingest = WarningCollection::ingest(&options);
+    for line in log.split_inclusive('\n') {
+        ingest.push(line);
+    }
+    assert_eq!(ingest.finish().files, result.files);
+}
+
+#[test]
+fn unwrap_wrapped_warnings() {
+    let log = "\
+/path/to/file1.c:12:3: warning: comparison of integer expressions of different s
+ignedness: 'int' and 'unsigned int' [-Wsign-compare]
+   12 |   if (i < n) {
+      |         ^
+/path/to/file2.c:4:1: warning: unused function 'f' [-Wunused-function]
+/path/to/file3.swift:8:5: warning: variable 'total' was never mutated
+/path/to/file3.swift:9:5: warning: initialization of immutable value 'result' wa
+s never used; consider replacing with assignment to '_' or removing it
+";
+    let wrapped = WarningCollection::new(log, 5, &[] as &[String]);
+    assert!(!wrapped.names.contains_key("sign-compare"));
+
+    let options = ParseOptions {
+        unwrap_lines: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.names["sign-compare"], 1);
+    assert_eq!(result.names["unused-function"], 1);
+    assert_eq!(result.names["variable-was-never"], 1);
+    assert_eq!(result.warnings[0].line, Some(12));
+    assert_eq!(result.names["initialization-of-immutable"], 1);
+    assert_eq!(result.total(), 4);
+
+    assert!(matches!(
+        unwrap_lines("/path/to/file2.c:4:1: warning: unused function 'f' [-Wunused-function]\n"),
+        std::borrow::Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn strip_flag_prefixes() {
+    let result = WarningCollection::new(
+        "/path/to/file1.c:12:3: remark: foo inlined into bar [-Rpass=inline]
+/path/to/file1.c:20:3: warning: doing some bad thing [-Wbad-thing]
+/path/to/file1.c:30:3: error: doing some bad thing [-Werror=bad-thing]
+",
+        5,
+        &[] as &[String],
+    );
+    assert_eq!(
+        result.names,
+        HashMap::from([("pass=inline".to_string(), 1), ("bad-thing".to_string(), 2)])
+    );
+    assert_eq!(result.warnings[0].toolchain, "clang");
+
+    let log = "MYTOOL src/file1.c: [+Xfoo]
+MYTOOL src/file2.c: [-Wbar]
+";
+    let parser = CustomParser::new(r"(?m)^MYTOOL (?P<file>\S+): \[(?P<name>[^\]]*)\]").unwrap();
+    let options = ParseOptions {
+        custom_parsers: vec![parser.clone()],
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.warnings[0].name, "+Xfoo");
+    assert_eq!(result.warnings[1].name, "bar");
+
+    let options = ParseOptions {
+        custom_parsers: vec![parser.with_flag_prefix("+X")],
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.warnings[0].name, "foo");
+    assert_eq!(result.warnings[1].name, "-Wbar");
+}
+
+#[test]
+fn custom_regex_missing_group() {
+    let result = CustomParser::new(r"(?P<file>\S+): (?P<message>.*)");
+    assert!(matches!(result, Err(Error::MissingGroup("name"))));
+}
+
+#[test]
+fn hotspots_prefer_spread_out_directories() {
+    let result = WarningCollection::new(
+        "/path/to/dir1/file1.c:1:1: warning: bad [-Wbad]
+/path/to/dir1/file1.c:2:1: warning: bad [-Wbad]
+/path/to/dir2/file1.c:1:1: warning: bad [-Wbad]
+/path/to/dir2/file2.c:1:1: warning: bad [-Wbad]
+/path/to/dir3/file1.c:1:1: warning: bad [-Wbad]
+/path/to/dir4/file1.c:1:1: warning: bad [-Wbad]
+",
+        5,
+        &["foo"],
+    );
+
+    let hotspots = score_warning_hotspots(&result.warnings);
+    assert_eq!(hotspots[&PathBuf::from("/path/to/dir1")], 2);
+    assert_eq!(hotspots[&PathBuf::from("/path/to/dir2")], 4);
+
+    let options = ReportOptions {
+        hotspots: true,
+        ..Default::default()
+    };
+    let report = result.report(&options).to_string();
+    assert!(report.ends_with(
+        "Hotspots:
+4  /path/to/dir2
+2  /path/to/dir1
+1  /path/to/dir3
+1  /path/to/dir4
+4  Total
+"
+    ));
+}
+
+#[test]
+fn fold_keyword_case() {
+    let content = "/path/to/file1.c:1:1: warning: bad [-Wbad]
+    1 |     Buffer = buffer + BUFFER;
+      |     ^~~~~~
+";
+    let options = ParseOptions {
+        fold_keyword_case: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(content, &options);
+
+    assert_eq!(result.keywords, HashMap::from([("buffer".to_string(), 3)]));
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["Buffer", "buffer", "BUFFER"]
+    );
+
+    let result = WarningCollection::with_options(content, &ParseOptions::default());
+    assert_eq!(result.keywords.len(), 3);
+}
+
+#[test]
+fn check_warning_budgets() {
+    let total: Budget = "5".parse().unwrap();
+    let check = TEST_WARNINGS.check_budget(&total);
+    assert!(check.passed());
+    assert_eq!(check.to_string(), "OK: 4/5");
+
+    let total: Budget = "3".parse().unwrap();
+    let check = TEST_WARNINGS.check_budget(&total);
+    assert!(!check.passed());
+    assert_eq!(check.to_string(), "FAIL: 4/3 (over budget by 1)");
+
+    let name: Budget = "horrible-stuff=0".parse().unwrap();
+    let check = TEST_WARNINGS.check_budget(&name);
+    assert_eq!(
+        check.to_string(),
+        "FAIL: horrible-stuff 2/0 (over budget by 2)"
+    );
+
+    let name: Budget = "unused=0".parse().unwrap();
+    assert!(TEST_WARNINGS.check_budget(&name).passed());
+
+    assert!("unused=lots".parse::<Budget>().is_err());
+}
+
+#[test]
+fn reject_budgets_without_a_name_or_below_zero() {
+    assert!(matches!(
+        "=5".parse::<Budget>(),
+        Err(Error::InvalidBudget(budget)) if budget == "=5"
+    ));
+    assert!(matches!(
+        "-1".parse::<Budget>(),
+        Err(Error::InvalidBudget(_))
+    ));
+    assert!(matches!(
+        "unused=-3".parse::<Budget>(),
+        Err(Error::InvalidBudget(_))
+    ));
+    assert!("unused=0".parse::<Budget>().is_ok());
+}
+
+#[test]
+fn add_warning_diffs() {
+    let lhs = WarningCollectionDiff {
+        names: HashMap::from([
+            ("bad-thing".to_string(), 2),
+            ("horrible-stuff".to_string(), -1),
+        ]),
+        files: HashMap::from([(PathBuf::from("/path/to/dir1/file1.c"), 1)]),
+        directories: HashMap::from([(PathBuf::from("/path/to/dir1"), 1)]),
+        basenames: HashMap::new(),
+        keywords: HashMap::new(),
+    };
+    let rhs = WarningCollectionDiff {
+        names: HashMap::from([("horrible-stuff".to_string(), 1)]),
+        files: HashMap::from([(PathBuf::from("/path/to/dir2/file2.c"), 1)]),
+        directories: HashMap::from([(PathBuf::from("/path/to/dir1"), 1)]),
+        basenames: HashMap::new(),
+        keywords: HashMap::from([("stuff".to_string(), 1)]),
+    };
+
+    let expected = WarningCollectionDiff {
+        names: HashMap::from([("bad-thing".to_string(), 2)]),
+        files: HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.c"), 1),
+            (PathBuf::from("/path/to/dir2/file2.c"), 1),
+        ]),
+        directories: HashMap::from([(PathBuf::from("/path/to/dir1"), 2)]),
+        basenames: HashMap::new(),
+        keywords: HashMap::from([("stuff".to_string(), 1)]),
+    };
+
+    assert_eq!(lhs + rhs, expected);
+}
+
+#[test]
+fn detect_toolchains() {
+    let result = WarningCollection::new(
+        "/path/to/file1.f90:235:36:
+
+  235 |     horrible = stuff
+      |                1
+Warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+  697 |     horrible = stuff;
+      |                ^~~
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+  715 |       horrible = stuff[i];
+      |                  ^~~
+",
+        5,
+        &["foo"],
+    );
+    assert_eq!(
+        result.detected_toolchains(),
+        vec_of_strings!["gcc", "gfortran"]
+    );
+    assert!(result
+        .to_string()
+        .starts_with("Detected: gcc, gfortran\n\nWarnings:\n"));
+
+    let result = WarningCollection::new(
+        "/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+    horrible = stuff;
+               ^~~~~
+1 warning generated.
+",
+        5,
+        &["foo"],
+    );
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["clang"]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_round_trip() {
+    let json = serde_json::to_string(&*TEST_WARNINGS).unwrap();
+    let result: WarningCollection = serde_json::from_str(&json).unwrap();
+    assert_eq!(result, *TEST_WARNINGS);
+
+    let diff = TEST_WARNINGS.diff(&WarningCollection::new("", 5, &["foo"]));
+    let json = serde_json::to_string(&diff).unwrap();
+    let result: WarningCollectionDiff = serde_json::from_str(&json).unwrap();
+    assert_eq!(result, diff);
+
+    // Unchanged counts are left out entirely
+    let mut fewer = TEST_WARNINGS.clone();
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    let json = serde_json::to_string(&TEST_WARNINGS.diff(&fewer)).unwrap();
+    assert!(json.contains("bad-thing"));
+    assert!(!json.contains("horrible-stuff"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn recount_untrusted_json() {
+    let mut tampered = TEST_WARNINGS.clone();
+    tampered.names.insert("bad-thing".to_string(), 100);
+    let json = serde_json::to_string(&tampered).unwrap();
+
+    let mut result: WarningCollection = serde_json::from_str(&json).unwrap();
+    assert_eq!(result.names["bad-thing"], 100);
+    result.recount();
+    assert_eq!(result, *TEST_WARNINGS);
+}
+
+#[test]
+fn paths_with_spaces() {
+    let log = "\
+/home/me/My Project/src/a.c:10:5: warning: unused variable 'x' [-Wunused-variable]
+   10 |   int x;
+      |       ^
+/home/me/My Project/src/b.f90(12): warning #6843: A dummy argument is unused
+/home/me/My Project/src/c.swift:3:7: warning: variable 'y' was never used
+/home/me/My Project/src/d.go:4:2: unreachable code
+/home/me/My Project/src/e.c:3:9: note: #pragma message: TODO
+    foo.c:1:2: warning: indented, so not a location [-Wfoo]
+";
+    let options = ParseOptions {
+        include_pragmas: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    let files = result
+        .warnings
+        .iter()
+        .map(|warning| warning.file.display().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        [
+            "/home/me/My Project/src/a.c",
+            "/home/me/My Project/src/b.f90",
+            "/home/me/My Project/src/d.go",
+            "/home/me/My Project/src/c.swift",
+            "/home/me/My Project/src/e.c",
+        ]
+    );
+    assert_eq!(result.names["unused-variable"], 1);
+    assert_eq!(warning_message(log), Some("unused variable 'x'"));
+}
+
+#[test]
+fn top_level_files_in_current_directory() {
+    let result = WarningCollection::new(
+        "file1.c:1:1: warning: bad [-Wbad]
+file2.c:1:1: warning: bad [-Wbad]
+/path/to/file3.c:1:1: warning: bad [-Wbad]
+",
+        5,
+        &["foo"],
+    );
+
+    assert_eq!(
+        result.directories,
+        HashMap::from([(PathBuf::from("."), 2), (PathBuf::from("/path/to"), 1)])
+    );
+}
+
+#[test]
+fn sort_names_alphabetically() {
+    let options = ReportOptions {
+        names_sort: SortOrder::Key,
+        ..Default::default()
+    };
+    let report = TEST_WARNINGS.report(&options).to_string();
+    assert!(report.contains(
+        "Warnings:
+1  bad-thing
+1  dont-like-this
+2  horrible-stuff
+4  Total
+"
+    ));
+}
+
+#[test]
+fn sample_every_other_warning() {
+    let log = "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:340:27: warning: don't like this [-Wdont-like-this]
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:720:18: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let options = ParseOptions {
+        sample_every: Some(2),
+        ..Default::default()
+    };
+    let sample = WarningCollection::with_options(log, &options);
+    assert_eq!(sample.warnings.len(), 3);
+    assert_eq!(sample.sample_every(), Some(2));
+    assert_eq!(
+        sample.names,
+        HashMap::from([
+            ("bad-thing".to_string(), 2),
+            ("horrible-stuff".to_string(), 4),
+        ])
+    );
+    assert_eq!(sample.total(), 6);
+
+    let report = sample.report(&ReportOptions::default()).to_string();
+    assert!(report.contains("Estimated from 1 in 2 warnings, with counts scaled up to match\n"));
+
+    let all = WarningCollection::with_options(log, &ParseOptions::default());
+    assert_eq!(all.sample_every(), None);
+    let report = all.report(&ReportOptions::default()).to_string();
+    assert!(!report.contains("Estimated"));
+}
+
+#[test]
+fn sample_in_log_order() {
+    let log = "\
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+Warning: /path/to/dir3/file4.f90, line 7: Unused dummy variable ZANG
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+Warning: /path/to/dir3/file4.f90, line 9: Unused dummy variable ZING
+";
+    let options = ParseOptions {
+        sample_every: Some(2),
+        ..Default::default()
+    };
+    let sample = WarningCollection::with_options(log, &options);
+    let names = sample
+        .warnings
+        .iter()
+        .map(|warning| warning.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["bad-thing", "horrible-stuff"]);
+
+    // The stride carries on from one chunk to the next
+    let mut ingest = WarningCollection::ingest(&options);
+    for line in log.split_inclusive('\n') {
+        ingest.push(line);
+    }
+    assert_eq!(ingest.finish().warnings, sample.warnings);
+}
+
+#[test]
+fn sort_directories_by_path() {
+    let warnings = WarningCollection::from_warnings(
+        [
+            "src/a-b/x.c",
+            "src/a/y.c",
+            "src/a/z.c",
+            "src/ab/v.c",
+            "src/ab/w.c",
+        ]
+        .into_iter()
+        .map(|file| Warning {
+            file: PathBuf::from(file),
+            line: Some(1),
+            column: None,
+            name: String::from("bad-thing"),
+            keywords: Vec::new(),
+            toolchain: String::from("gcc"),
+            raw: None,
+            message: None,
+        })
+        .collect(),
+        &ParseOptions::default(),
+    );
+    let options = ReportOptions {
+        dirs_sort: SortOrder::Key,
+        ..Default::default()
+    };
+    let report = warnings.report(&options).to_string();
+    assert!(report.contains(
+        "Directories:
+2  src/a
+1  src/a-b
+2  src/ab
+3  Total
+"
+    ));
+
+    let report = warnings.report(&ReportOptions::default()).to_string();
+    assert!(report.contains("Directories:\n2  src/a\n2  src/ab\n1  src/a-b\n"));
+}
+
+#[test]
+fn oneline_summary() {
+    assert_eq!(
+        TEST_WARNINGS.oneline(),
+        "4 warnings | top: horrible-stuff(2), file /path/to/dir2/file2.c(2), \
+         dir /path/to/dir2(3), keyword horrible(3)"
+    );
+
+    let options = ReportOptions {
+        oneline: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        TEST_WARNINGS.report(&options).to_string(),
+        TEST_WARNINGS.oneline()
+    );
+
+    let empty = WarningCollection::new("", 5, &[] as &[String]);
+    assert_eq!(empty.oneline(), "0 warnings");
+}
+
+#[test]
+fn distinct_stats() {
+    let stats = TEST_WARNINGS.stats();
+    assert_eq!(
+        stats,
+        Stats {
+            warnings: 4,
+            names: 3,
+            files: 3,
+            directories: 2,
+            keywords: 5,
+        }
+    );
+
+    let options = ReportOptions {
+        stats: true,
+        ..Default::default()
+    };
+    let report = TEST_WARNINGS.report(&options).to_string();
+    assert!(report.contains(
+        "4 warnings: 3 distinct warning types across 3 files in 2 directories, with 5 distinct keywords\n\nWarnings:"
+    ));
+}
+
+#[test]
+fn canonicalize_existing_files() {
+    let dir = std::env::temp_dir().join("warnsum_canonicalize_existing_files");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let file = dir.join("file1.c");
+    std::fs::write(&file, "").unwrap();
+    let file = std::fs::canonicalize(file).unwrap();
+
+    let content = format!(
+        "{0}/file1.c:1:1: warning: bad [-Wbad]
+{0}/sub/../file1.c:2:1: warning: bad [-Wbad]
+{0}/./missing.c:3:1: warning: bad [-Wbad]
+",
+        dir.display()
+    );
+    let options = ParseOptions {
+        canonicalize_existing: true,
+        ..Default::default()
+    };
+    let mut result = WarningCollection::with_options(&content, &options);
+    result.merge(WarningCollection::with_options(&content, &options));
+
+    let missing = normalize_lexically(&dir.join("missing.c"));
+    assert_eq!(result.files, HashMap::from([(file, 4), (missing, 2)]));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_one_category() {
+    let options = ReportOptions {
+        only: Some(Category::Files),
+        ..Default::default()
+    };
+    let expected = "2  /path/to/dir2/file2.c
+1  /path/to/dir1/file1.c
+1  /path/to/dir2/file1.c
+";
+    assert_eq!(TEST_WARNINGS.report(&options).to_string(), expected);
+
+    let options = ReportOptions {
+        with_total: true,
+        ..options
+    };
+    assert_eq!(
+        TEST_WARNINGS.report(&options).to_string(),
+        format!("{expected}3  Total\n")
+    );
+}
+
+#[test]
+fn report_parsing_progress() {
+    let content = "/path/to/file1.c:1:1: warning: bad [-Wbad]
+/path/to/file2.c:1:1: warning: bad [-Wbad]
+Done
+";
+    let mut offsets = Vec::new();
+    let result = WarningCollection::with_progress(content, &ParseOptions::default(), |offset| {
+        offsets.push(offset)
+    });
+
+    assert_eq!(result.total(), 2);
+    assert_eq!(offsets, vec![42, 85, content.len()]);
+}
+
+#[test]
+fn numeric_keywords() {
+    let content = "/path/to/file1.c:12345:1: warning: bad [-Wbad]
+ 12345 |     uint16bit = 0xDEADBEEF + 16bit;
+       |                 ^~~~~~~~~~
+";
+    let result = WarningCollection::new(content, 5, &["foo"]);
+    assert_eq!(result.warnings[0].keywords, vec_of_strings!["uint16bit"]);
+
+    let options = ParseOptions {
+        keyword_include_numeric: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(content, &options);
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["uint16bit", "0xDEADBEEF", "16bit"]
+    );
+}
+
+#[test]
+fn collapse_repeated_warnings() {
+    let volume = TEST_WARNINGS.report(&ReportOptions::default()).to_string();
+    assert!(volume.contains("2  horrible-stuff\n"));
+    assert!(volume.contains("2  /path/to/dir2/file2.c\n"));
+    assert!(!volume.contains("repeats"));
+
+    let options = ReportOptions {
+        collapse_repeats: true,
+        ..Default::default()
+    };
+    let breadth = TEST_WARNINGS.report(&options).to_string();
+    assert!(breadth.contains("1  horrible-stuff\n"));
+    assert!(breadth.contains("1  /path/to/dir2/file2.c\n"));
+    assert!(breadth.contains("leaving out 1 repeats\n"));
+    // Other sections still count every warning
+    assert!(breadth.contains("3  /path/to/dir2\n"));
+}
+
+#[test]
+fn tiebreak_by_first_seen() {
+    let log = "/path/to/zeta.c:1:1: warning: zzz [-Wzzz]
+/path/to/alpha.c:1:1: warning: aaa [-Waaa]
+";
+    let collection = WarningCollection::new(log, 5, &[] as &[String]);
+    let section = |tiebreak| {
+        let options = ReportOptions {
+            sections: vec![Category::Names, Category::Files],
+            tiebreak,
+            ..Default::default()
+        };
+        collection.report(&options).to_string()
+    };
+
+    let alpha = section(Tiebreak::Alpha);
+    assert!(alpha.find("aaa") < alpha.find("zzz"));
+    assert!(alpha.find("alpha.c") < alpha.find("zeta.c"));
+
+    let first_seen = section(Tiebreak::FirstSeen);
+    assert!(first_seen.find("zzz") < first_seen.find("aaa"));
+    assert!(first_seen.find("zeta.c") < first_seen.find("alpha.c"));
+}
+
+#[test]
+fn ranked_names() {
+    let counts = (1..=10)
+        .map(|count| (format!("name{count:02}"), count))
+        .collect::<HashMap<_, _>>();
+    let result = make_section(&counts, 0, false, SortOrder::Count)
+        .ranked()
+        .to_string();
+    let lines = result.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "#1   10  name10");
+    assert_eq!(lines[9], "#10   1  name01");
+    assert_eq!(lines[10], "     55  Total");
+}
+
+#[test]
+fn line_templates() {
+    let counts = HashMap::from([("unused", 3), ("shadow", 1)]);
+    let template = "{rank}. {key}: {count} ({percent}%) {bar}"
+        .parse::<LineTemplate>()
+        .unwrap();
+    let result = make_section(&counts, 0, false, SortOrder::Count)
+        .templated(&template, None)
+        .to_string();
+    let lines = result.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        format!("1. unused: 3 (75%) {}", "#".repeat(BAR_WIDTH))
+    );
+    assert_eq!(lines[1], "2. shadow: 1 (25%) ######");
+    assert_eq!(lines[2], "4  Total");
+
+    assert!(matches!(
+        "{count} {name}".parse::<LineTemplate>(),
+        Err(Error::InvalidToken(token)) if token == "{name}"
+    ));
+    assert!(matches!(
+        "{count".parse::<LineTemplate>(),
+        Err(Error::InvalidToken(token)) if token == "{count"
+    ));
+}
+
+#[test]
+fn glob_patterns() {
+    assert!(glob_match("unused", "unused"));
+    assert!(!glob_match("unused", "unused-variable"));
+    assert!(glob_match("unused-*", "unused-variable"));
+    assert!(!glob_match("unused-*", "maybe-unused"));
+    assert!(glob_match("*unused*", "maybe-unused-thing"));
+    assert!(glob_match("a*b*c", "abbc"));
+    assert!(!glob_match("a*b*c", "acb"));
+    assert!(!glob_match("ab*ba", "aba"));
+}
+
+#[test]
+fn find_banned_names() {
+    assert_eq!(
+        TEST_WARNINGS.find_names(&["horrible-*", "bad-thing", "missing"]),
+        vec![
+            ("bad-thing".to_string(), 1),
+            ("horrible-stuff".to_string(), 2)
+        ]
+    );
+    assert!(TEST_WARNINGS.find_names(&["unused-*"]).is_empty());
+}
+
+#[test]
+fn collection_as_csv() {
+    let csv = TEST_WARNINGS.to_csv();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "category,key,count");
+    assert_eq!(lines[1], "names,horrible-stuff,2");
+    assert_eq!(lines[4], "files,/path/to/dir2/file2.c,2");
+    assert_eq!(lines.last(), Some(&"keywords,zang,1"));
+    assert_eq!(lines.len(), 16);
+}
+
+#[test]
+fn collection_as_key_values() {
+    let kv = TEST_WARNINGS.to_key_values();
+    let lines = kv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "total=4");
+    assert!(lines.contains(&"names.horrible-stuff=2"));
+    assert!(lines.contains(&"files./path/to/dir2/file2.c=2"));
+    assert!(lines.contains(&"directories./path/to/dir1=1"));
+    assert!(lines.contains(&"keywords.zang=1"));
+    assert_eq!(lines.len(), 16);
+
+    let warnings = WarningCollection::from_warnings(
+        vec![Warning {
+            file: PathBuf::from("/path/to/odd dir/a=\"b\".c"),
+            ..TEST_WARNINGS.warnings[0].clone()
+        }],
+        &ParseOptions::default(),
+    );
+    assert!(warnings
+        .to_key_values()
+        .contains("\nfiles.\"/path/to/odd dir/a=\\\"b\\\".c\"=1\n"));
+}
+
+#[test]
+fn collection_as_prometheus() {
+    let metrics = TEST_WARNINGS.to_prometheus();
+    let lines = metrics.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..4],
+        [
+            "# HELP warnsum_warnings_total Number of compiler warnings, in total and by name",
+            "# TYPE warnsum_warnings_total gauge",
+            "warnsum_warnings_total 4",
+            "warnsum_warnings_total{name=\"horrible-stuff\"} 2",
+        ]
+    );
+    assert!(lines.contains(&"warnsum_warnings_by_file{file=\"/path/to/dir2/file2.c\"} 2"));
+    assert!(lines.contains(&"# TYPE warnsum_warnings_by_keyword gauge"));
+    assert!(lines.contains(&"warnsum_warnings_by_keyword{keyword=\"zang\"} 1"));
+
+    let warnings = WarningCollection::from_warnings(
+        vec![Warning {
+            file: PathBuf::from(r#"/path/to/"odd"\file1.c"#),
+            ..TEST_WARNINGS.warnings[0].clone()
+        }],
+        &ParseOptions::default(),
+    );
+    assert!(warnings
+        .to_prometheus()
+        .contains(r#"warnsum_warnings_by_file{file="/path/to/\"odd\"\\file1.c"} 1"#));
+}
+
+#[test]
+fn collection_as_tsv() {
+    // The parsers can't find files with tabs in, but JSON snapshots can
+    let warnings = WarningCollection::from_warnings(
+        vec![Warning {
+            file: PathBuf::from("/path/to/odd\tdir/file1.c"),
+            ..TEST_WARNINGS.warnings[0].clone()
+        }],
+        &ParseOptions::default(),
+    );
+    let tsv = warnings.to_delimited(b'\t');
+    let lines = tsv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "category\tkey\tcount");
+    assert_eq!(lines[1], "names\tbad-thing\t1");
+    assert_eq!(lines[2], "files\t\"/path/to/odd\tdir/file1.c\"\t1");
+
+    let reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .from_reader(tsv.as_bytes());
+    let files = reader
+        .into_records()
+        .map(Result::unwrap)
+        .filter(|record| &record[0] == "files")
+        .map(|record| record[1].to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["/path/to/odd\tdir/file1.c"]);
+
+    assert_eq!(warnings.to_delimited(b','), warnings.to_csv());
+}
+
+#[test]
+fn keyword_length_histogram() {
+    let lengths = KeywordLengths::new(&TEST_WARNINGS);
+    assert_eq!(lengths.get(4), Some((3, 5)));
+    assert_eq!(lengths.get(5), Some((1, 2)));
+    assert_eq!(lengths.get(8), Some((1, 3)));
+    assert_eq!(lengths.get(6), None);
+    assert_eq!(
+        lengths.to_string(),
+        "\
+Length  Keywords  Occurrences
+     4         3            5
+     5         1            2
+     8         1            3
+"
+    );
+}
+
+#[test]
+fn trend_over_snapshots() {
+    let mut trend = Trend::new();
+    trend.push("day1", &TEST_WARNINGS);
+    trend.push("day2", &WarningCollection::new("", 5, &["foo"]));
+
+    assert_eq!(
+        trend.to_string(),
+        "Snapshot  Warnings  Names  Files  Directories  Keywords
+day1             4      3      3            2         5
+day2             0      0      0            0         0
+"
+    );
+    assert_eq!(
+        trend.to_csv(),
+        "snapshot,warnings,names,files,directories,keywords
+day1,4,3,3,2,5
+day2,0,0,0,0,0
+"
+    );
+}
+
+#[test]
+fn collection_from_file() {
+    let path = std::env::temp_dir().join("warnsum_collection_from_file.log");
+    std::fs::write(&path, "/path/to/file1.c:1:1: warning: bad [-Wbad]\n").unwrap();
+    let result = WarningCollection::from_file(&path, &ParseOptions::default()).unwrap();
+    assert_eq!(result.total(), 1);
+    std::fs::remove_file(&path).unwrap();
+
+    let result = WarningCollection::from_file(&path, &ParseOptions::default());
+    assert!(matches!(result, Err(Error::Io { .. })));
+}
+
+#[test]
+fn dry_run_parsers() {
+    let options = ParseOptions {
+        custom_parsers: vec![
+            CustomParser::new(r"(?m)^MYTOOL (?P<file>\S+): (?P<name>W\d+)").unwrap(),
+        ],
+        ..Default::default()
+    };
+    let result = DryRun::new(
+        "/path/to/file1.c:1:1: warning: bad [-Wbad]
+/path/to/file2.c:1:1: warning: worse [-Wworse]
+MYTOOL src/file3.c: W101
+",
+        &options,
+    );
+
+    assert_eq!(
+        result.to_string(),
+        "Matches:
+2  gcc
+1  custom
+
+Samples:
+/path/to/file1.c  bad
+/path/to/file2.c  worse
+src/file3.c  W101
+"
+    );
+}
+
+#[test]
+fn format_hash_map_with_zero_total() {
+    let counts = HashMap::from([("result1".to_string(), 0), ("result2".to_string(), 0)]);
+    let result = make_section(&counts, 0, false, SortOrder::Count).to_string();
+    assert_eq!(result, "0  result1\n0  result2\n0  Total");
+
+    let counts = HashMap::from([("result1".to_string(), -3), ("result2".to_string(), 1)]);
+    let result = make_section(&counts, 0, false, SortOrder::Count).to_string();
+    assert_eq!(result, "1  result2\n-3  result1\n-2  Total");
+}
+
+#[test]
+fn weighted_scores() {
+    let weights: Weights = "# Weights
+bad-thing = 10
+"
+    .parse()
+    .unwrap();
+
+    let scores = score_warning_files(&TEST_WARNINGS.warnings, &weights);
+    assert_eq!(scores[&PathBuf::from("/path/to/dir1/file1.c")], 10);
+    assert_eq!(scores[&PathBuf::from("/path/to/dir2/file1.c")], 1);
+    assert_eq!(scores[&PathBuf::from("/path/to/dir2/file2.c")], 2);
+    assert_eq!(TEST_WARNINGS.weighted_score(&weights), 13);
+
+    let options = ReportOptions {
+        weights: Some(weights),
+        ..Default::default()
+    };
+    let report = TEST_WARNINGS.report(&options).to_string();
+    assert!(report.contains("Weighted score: 13\n"));
+    assert!(report.ends_with(
+        "Scores:
+10  /path/to/dir1/file1.c
+ 2  /path/to/dir2/file2.c
+ 1  /path/to/dir2/file1.c
+13  Total
+"
+    ));
+
+    assert!(matches!(
+        "# Weights\n\nbad-thing=lots".parse::<Weights>(),
+        Err(Error::InvalidMapping { line: 3, .. })
+    ));
+}
+
+#[test]
+fn alias_warning_names() {
+    let aliases: Aliases = "\
+# gcc 4 spelling
+maybe-uninitialized = uninitialized
+uninitialised = maybe-uninitialized
+"
+    .parse()
+    .unwrap();
+    let options = ParseOptions {
+        aliases,
+        ..Default::default()
+    };
+    let warnings = WarningCollection::with_options(
+        "/path/to/file1.c:235:36: warning: horrible may be used [-Wmaybe-uninitialized]
+/path/to/file1.c:340:27: warning: horrible is used [-Wuninitialized]
+/path/to/file2.c:697:16: warning: horrible is used [-Wuninitialised]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+        &options,
+    );
+    assert_eq!(
+        warnings.names,
+        HashMap::from([
+            ("uninitialized".to_string(), 3),
+            ("horrible-stuff".to_string(), 1),
+        ])
+    );
+
+    assert!(check_mapping("a=b\n\n# comment\nb=c").is_ok());
+    assert!(matches!(
+        check_mapping("a=b\n\n# comment\na=c"),
+        Err(Error::DuplicateMapping { line: 4, first: 1, key }) if key == "a"
+    ));
+
+    let cycle: Aliases = "a=b\nb=a".parse().unwrap();
+    assert_eq!(cycle.resolve("a"), "a");
+
+    assert!(matches!(
+        "bad-thing=".parse::<Aliases>(),
+        Err(Error::InvalidMapping { line: 1, .. })
+    ));
+}
+
+#[test]
+fn keywords_from_message() {
+    let log = "/path/to/file1.c:12:9: warning: comparison of integer expressions of different signedness [-Wsign-compare]
+   12 |     if (count < limit) {
+      |               ^
+";
+    let keywords = |keyword_source| {
+        let options = ParseOptions {
+            keyword_source,
+            ..Default::default()
+        };
+        let mut keywords = WarningCollection::with_options(log, &options)
+            .keywords
+            .into_keys()
+            .collect::<Vec<_>>();
+        keywords.sort();
+        keywords
+    };
+    assert_eq!(keywords(KeywordSource::Code), ["count", "limit"]);
+    assert_eq!(
+        keywords(KeywordSource::Message),
+        [
+            "comparison",
+            "different",
+            "expressions",
+            "integer",
+            "signedness"
+        ]
+    );
+    let both = keywords(KeywordSource::Both);
+    assert!(both.contains(&"signedness".to_string()));
+    assert!(both.contains(&"limit".to_string()));
+    assert_eq!(both.len(), 7);
+}
+
+#[test]
+fn unique_keywords_per_warning() {
+    let content = "/path/to/file1.c:1:1: warning: bad [-Wbad]
+    1 |     horrible = horrible + stuff + horrible;
+      |     ^~~~~~~~
+";
+    let result = WarningCollection::new(content, 5, &["foo"]);
+    assert_eq!(result.keywords["horrible"], 3);
+
+    let options = ParseOptions {
+        unique_keywords_per_warning: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(content, &options);
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["horrible", "stuff"]
+    );
+    assert_eq!(result.keywords["horrible"], 1);
+}
+
+#[test]
+fn reorder_sections() {
+    let headings = |order| {
+        let options = ReportOptions {
+            order,
+            ..Default::default()
+        };
+        TEST_WARNINGS
+            .report(&options)
+            .to_string()
+            .lines()
+            .filter(|line| line.ends_with(':'))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        headings(Vec::new()),
+        ["Warnings:", "Files:", "Directories:", "Keywords:"]
+    );
+    assert_eq!(
+        headings(vec![Category::Files, Category::Names]),
+        ["Files:", "Warnings:", "Directories:", "Keywords:"]
+    );
+    assert_eq!(
+        headings(vec![Category::Keywords, Category::Tree, Category::Files]),
+        ["Keywords:", "Files:", "Warnings:", "Directories:"]
+    );
+}
+
+#[test]
+fn count_basenames_across_directories() {
+    assert_eq!(
+        count_warning_basenames(&TEST_WARNINGS.warnings),
+        TEST_WARNINGS.basenames
+    );
+
+    let options = ReportOptions {
+        sections: vec![Category::Filenames, Category::Names],
+        ..Default::default()
+    };
+    assert_eq!(
+        TEST_WARNINGS.report(&options).to_string(),
+        "Detected: gcc
+
+Warnings:
+2  horrible-stuff
+1  bad-thing
+1  dont-like-this
+4  Total
+
+Filenames:
+2  file1.c
+2  file2.c
+2  Total
+"
+    );
+}
+
+#[test]
+fn truncate_long_paths() {
+    assert_eq!(truncate_middle("short.c", 10), "short.c");
+    assert_eq!(
+        truncate_middle("src/very/deeply/nested/path/socket.c", 20),
+        "src/v...ath/socket.c"
+    );
+    assert_eq!(truncate_middle("socket.c", 2), "..");
+
+    let files = HashMap::from([
+        (PathBuf::from("src/very/deeply/nested/path/socket.c"), 12),
+        (PathBuf::from("main.c"), 1),
+    ]);
+    let section = make_section(&files, 10, true, SortOrder::Count).truncated(24);
+    assert_eq!(
+        section.to_string(),
+        "12  src/v...ath/socket.c
+ 1  main.c
+ 2  Total"
+    );
+    assert!(section.lines.iter().all(|line| line.chars().count() <= 24));
+}
+
+#[test]
+fn crosstab_names_and_keywords() {
+    let crosstab = Crosstab::new(&TEST_WARNINGS, 2, 2);
+    assert_eq!(
+        crosstab.to_string(),
+        "\
+Warning         horrible  stuff
+horrible-stuff         2      2
+bad-thing              1      0
+"
+    );
+    assert_eq!(
+        crosstab.to_csv(),
+        "Warning,horrible,stuff\nhorrible-stuff,2,2\nbad-thing,1,0\n"
+    );
+}
+
+#[test]
+fn ignore_locations_embedded_in_prose() {
+    let result = WarningCollection::new(
+        r"
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |     if (horrible) *foo = zing->zimb;
+      |                                1
+-- Note: an old build failed at foo:1:2: warning: this isn't real [-Wnot-real]
+",
+        5,
+        &[""],
+    );
+    assert_eq!(result.total(), 1);
+    assert_eq!(result.names, HashMap::from([("bad-thing".to_string(), 1)]));
+}
+
+#[test]
+fn explain_warning_name() {
+    let block = "\
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+  697 |     horrible = stuff;
+      |                ^~~";
+    let log = format!(
+        "\
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+{block}
+"
+    );
+
+    let options = ParseOptions {
+        keep_raw: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(&log, &options);
+    assert_eq!(result.explain("horrible-stuff"), Some(block));
+    assert_eq!(result.explain("missing"), None);
+
+    let result = WarningCollection::with_options(&log, &ParseOptions::default());
+    assert_eq!(result.explain("horrible-stuff"), None);
+}
+
+#[test]
+fn count_warnings_on_same_line() {
+    let mut warnings = TEST_WARNINGS.warnings.clone();
+    warnings[3].line = Some(697);
+    warnings[0].line = None;
+
+    assert_eq!(
+        count_warning_lines(&warnings),
+        HashMap::from([
+            ("/path/to/dir2/file1.c:340".to_string(), 1),
+            ("/path/to/dir2/file2.c:697".to_string(), 2),
+        ])
+    );
+}
+
+#[test]
+fn keywords_from_underlined_token() {
+    let log = "\
+/path/to/dir1/file1.c:10:5: warning: unused variable [-Wunused-variable]
+   10 |   int pointer = other_value;
+      |       ^~~~~~~
+";
+    let options = ParseOptions {
+        keyword_underlined_only: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.warnings[0].keywords, vec_of_strings!["pointer"]);
+
+    let result = WarningCollection::with_options(log, &ParseOptions::default());
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["pointer", "other_value"]
+    );
+
+    assert_eq!(
+        underlined_text(
+            "  12 |   x = left_side + right;",
+            "     |           ~~~~^~~~~"
+        ),
+        Some("left_side + right")
+    );
+}
+
+#[test]
+fn ingest_log_in_chunks() {
+    let mut log = String::new();
+    for index in 0..200 {
+        log.push_str(&format!(
+            "\
+/path/to/dir{index}/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |     if (horrible) *foo = zing->zimb;
+      |                                ^~~~~
+/path/to/dir{index}/file2.c:12:3: warning: unused variable 'stuff' [-Wunused-variable]
+/path/to/dir{index}/file1.f90(12): warning #6843: A dummy argument is not given a value.
+subroutine horrible_stuff(result)
+Warning: /path/to/dir{index}/file3.f90, line 7: Unused local variable ZING_ZIMB
+(./chapter{index}.tex
+LaTeX Warning: Reference `fig:zing' on page 1 undefined on input line {index}.
+)
+"
+        ));
+        log.push_str(TEST_FORTRAN_LOG);
+    }
+    log.push_str("400 warnings generated.\n");
+
+    let options = ParseOptions {
+        keyword_underlined_only: true,
+        ..Default::default()
+    };
+    let expected = WarningCollection::with_options(&log, &options);
+    assert_eq!(expected.total(), 200 * 9);
+    assert!(expected
+        .detected_toolchains()
+        .contains(&"clang".to_string()));
+
+    for chunk_size in [7, 64, 4096] {
+        let mut ingest = WarningCollection::ingest(&options);
+        let mut rest = log.as_str();
+        while !rest.is_empty() {
+            let (chunk, tail) = rest.split_at(chunk_size.min(rest.len()));
+            ingest.push(chunk);
+            rest = tail;
+        }
+        assert_eq!(ingest.finish(), expected, "chunks of {chunk_size} bytes");
+    }
+}
+
+#[test]
+fn keywords_from_numbered_marker() {
+    let options = ParseOptions {
+        keyword_len: 3,
+        keyword_underlined_only: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(TEST_FORTRAN_LOG, &options);
+    let keywords = result
+        .warnings
+        .iter()
+        .map(|warning| warning.keywords.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keywords,
+        [
+            vec_of_strings!["zimb"],
+            vec_of_strings!["foo"],
+            vec_of_strings!["stuff"],
+            vec_of_strings!["stuff"],
+        ]
+    );
+
+    assert_eq!(
+        underlined_text("  12 |   x = left_side + right;", "     |           1"),
+        Some("left_side")
+    );
+}
+
+#[test]
+fn read_intel_warnings() {
+    let result = WarningCollection::new(
+        "\
+/path/to/dir1/file1.f90(12): warning #6843: A dummy argument with an explicit INTENT(OUT) declaration is not given an explicit value.   [RESULT]
+subroutine horrible_stuff(result)
+------------------------^
+/path/to/dir2/file2.c(34): warning #177: variable \"stuff\" was declared but never referenced
+    int stuff;
+        ^
+",
+        5,
+        &[""],
+    );
+
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["intel"]);
+    assert_eq!(
+        result.names,
+        HashMap::from([("6843".to_string(), 1), ("177".to_string(), 1)])
+    );
+    assert_eq!(result.warnings[0].line, Some(12));
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["subroutine", "horrible_stuff", "result"]
+    );
+    assert_eq!(result.warnings[1].keywords, vec_of_strings!["stuff"]);
+}
+
+#[test]
+fn read_go_vet_warnings() {
+    let result = WarningCollection::new(
+        "\
+# example.com/project/net
+net/client.go:12:5: fmt.Printf format %d has arg horrible of wrong type string
+net/client.go:40:2: unreachable code
+",
+        5,
+        &[""],
+    );
+
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["go-vet"]);
+    assert_eq!(result.names, HashMap::from([("go-vet".to_string(), 2)]));
+    assert_eq!(result.warnings[0].line, Some(12));
+    assert_eq!(result.warnings[0].column, Some(5));
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["Printf", "format", "horrible", "wrong", "string"]
+    );
+}
+
+#[test]
+fn read_staticcheck_warnings() {
+    let result = WarningCollection::new(
+        "\
+net/client.go:12:5: should use time.Since instead of time.Now().Sub (S1012)
+net/client.go:20:9: this value of horrible is never used (SA4006)
+net/server.go:7:1: should not use dot imports (ST1001)
+",
+        5,
+        &[""],
+    );
+
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["staticcheck"]);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("S1012".to_string(), 1),
+            ("SA4006".to_string(), 1),
+            ("ST1001".to_string(), 1),
+        ])
+    );
+    assert_eq!(
+        result.warnings[1].keywords,
+        vec_of_strings!["value", "horrible", "never"]
+    );
+}
+
+#[test]
+fn stop_parsing_at_limits() {
+    let log = "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:340:27: warning: don't like this [-Wdont-like-this]
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let all = WarningCollection::with_options(log, &ParseOptions::default());
+    assert_eq!(all.total(), 4);
+    assert!(!all.is_truncated());
+
+    let options = ParseOptions {
+        max_warnings: Some(3),
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.total(), 3);
+    assert!(result.is_truncated());
+
+    let options = ParseOptions {
+        max_files: Some(2),
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.files.len(), 2);
+    assert_eq!(result.total(), 2);
+    assert!(result.is_truncated());
+
+    // Exactly at the limits isn't truncated
+    let options = ParseOptions {
+        max_files: Some(3),
+        max_warnings: Some(4),
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.total(), 4);
+    assert!(!result.is_truncated());
+}
+
+#[test]
+fn limits_keep_the_first_warnings_in_the_log() {
+    let log = "Warning: /path/to/dir1/file1.f90, line 12: Unused local variable HORRIBLE
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+Warning: /path/to/dir1/file1.f90, line 20: Variable STUFF set but never referenced
+/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let options = ParseOptions {
+        max_warnings: Some(3),
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    let lines = result
+        .warnings
+        .iter()
+        .map(|warning| warning.line)
+        .collect::<Vec<_>>();
+    // Each parser's warnings still come together
+    assert_eq!(lines, [Some(697), Some(12), Some(20)]);
+    assert!(result.is_truncated());
+
+    let mut ingest = WarningCollection::ingest(&options);
+    for line in log.split_inclusive('\n') {
+        ingest.push(line);
+    }
+    assert_eq!(ingest.finish(), result);
+}
+
+#[test]
+fn read_nag_warnings() {
+    let result = WarningCollection::new(
+        "\
+NAG Fortran Compiler Release 7.1(Hanzomon) Build 7101
+Warning: /path/to/dir1/file1.f90, line 12: Unused local variable HORRIBLE
+Warning: /path/to/dir1/file1.f90, line 20: Variable STUFF set but never referenced
+Warning: /path/to/dir2/file2.f90, line 7: Unused local variable ZING_ZIMB
+[NAG Fortran Compiler normal termination, 3 warnings]
+",
+        5,
+        &[""],
+    );
+
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["nag"]);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("unused-local-variable".to_string(), 2),
+            ("variable-set-but-never-referenced".to_string(), 1),
+        ])
+    );
+    assert_eq!(
+        result.files,
+        HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.f90"), 2),
+            (PathBuf::from("/path/to/dir2/file2.f90"), 1),
+        ])
+    );
+    assert_eq!(result.warnings[0].line, Some(12));
+}
+
+#[test]
+fn warning_name_stops_at_first_bracket() {
+    let warnings = WarningCollection::new(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wfoo] (see docs [here])
+/path/to/file1.c:340:27: warning: index [0] out of bounds [-Warray-bounds]
+/path/to/file2.c:697:16: error: just horrible stuff [-Werror=bar] [-Wbaz]
+",
+        5,
+        &[] as &[String],
+    );
+    let names = warnings
+        .warnings
+        .iter()
+        .map(|warning| warning.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["foo", "array-bounds", "bar"]);
+}
+
+#[test]
+fn keywords_after_text_following_name() {
+    let log = "/path/to/file1.c:10:5: warning: unused variable 'horrible' [-Wunused-variable] (see [docs])
+   10 |     int horrible;
+      |         ^~~~~~~~
+";
+    let warnings = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(warnings.warnings[0].name, "unused-variable");
+    assert_eq!(warnings.warnings[0].keywords, vec_of_strings!["horrible"]);
+}
+
+#[test]
+fn ignore_warning_names() {
+    let log = "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:340:27: warning: bad version of this [-Wbad-version]
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let options = ParseOptions {
+        ignored_names: vec_of_strings!["bad-*"],
+        ..Default::default()
+    };
+    let warnings = WarningCollection::with_options(log, &options);
+    assert_eq!(
+        warnings.names,
+        HashMap::from([("horrible-stuff".to_string(), 1)])
+    );
+    assert_eq!(
+        warnings.files,
+        HashMap::from([(PathBuf::from("/path/to/dir2/file2.c"), 1)])
+    );
+    assert_eq!(warnings.total(), 1);
+}
+
+#[test]
+fn exclude_line_ranges() {
+    let log = "/path/to/dir1/file1.c:99:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir1/file1.c:100:27: warning: bad version of this [-Wbad-version]
+/path/to/dir1/file1.c:150:27: warning: bad version of this [-Wbad-version]
+/path/to/dir1/file1.c:201:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file1.c:150:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file2.c:7:16: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let options = ParseOptions {
+        excluded_ranges: vec![
+            "dir1/file1.c:100-200".parse().unwrap(),
+            "/path/to/dir2/file2.c:7".parse().unwrap(),
+        ],
+        ..Default::default()
+    };
+    let warnings = WarningCollection::with_options(log, &options);
+    assert_eq!(
+        warnings.names,
+        HashMap::from([
+            ("bad-thing".to_string(), 1),
+            ("horrible-stuff".to_string(), 2),
+        ])
+    );
+    assert_eq!(
+        warnings.files,
+        HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.c"), 2),
+            (PathBuf::from("/path/to/dir2/file1.c"), 1),
+        ])
+    );
+    assert_eq!(warnings.total(), 3);
+
+    for invalid in ["file.c", "file.c:", ":1-2", "file.c:2-1", "file.c:a-b"] {
+        assert!(matches!(
+            invalid.parse::<LineRange>(),
+            Err(Error::InvalidLineRange(range)) if range == invalid
+        ));
+    }
+}
+
+#[test]
+fn sort_warnings_by_location() {
+    let log = "/path/to/dir2/file1.c:10:5: warning: bad version of this [-Wbad-version]
+/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:10:5: warning: another bad thing [-Wbad-thing]
+/path/to/dir1/file1.c:9:1: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/dir2/file1.c:2:7: warning: just horrible stuff [-Whorrible-stuff]
+";
+    let mut warnings = WarningCollection::new(log, 5, &[] as &[String]).warnings;
+    warnings.sort_by(Warning::location_cmp);
+    let locations = warnings
+        .iter()
+        .map(|warning| {
+            format!(
+                "{}:{}:{}",
+                warning.file.display(),
+                warning.line.unwrap(),
+                warning.name
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        [
+            "/path/to/dir1/file1.c:9:horrible-stuff",
+            "/path/to/dir1/file1.c:235:bad-thing",
+            "/path/to/dir2/file1.c:2:horrible-stuff",
+            "/path/to/dir2/file1.c:10:bad-thing",
+            "/path/to/dir2/file1.c:10:bad-version",
+        ]
+    );
+
+    let other_keywords = Warning {
+        keywords: vec_of_strings!["different"],
+        ..warnings[0].clone()
+    };
+    assert_eq!(
+        warnings[0].location_cmp(&other_keywords),
+        std::cmp::Ordering::Equal
+    );
+}
+
+#[test]
+fn cap_category_counts() {
+    let mut warnings = TEST_WARNINGS.clone();
+    warnings.cap_categories(2);
+
+    assert_eq!(
+        warnings.keywords,
+        HashMap::from([
+            ("horrible".to_string(), 3),
+            ("stuff".to_string(), 2),
+            ("(other)".to_string(), 5),
+        ])
+    );
+    assert_eq!(
+        warnings.names,
+        HashMap::from([
+            ("horrible-stuff".to_string(), 2),
+            ("bad-thing".to_string(), 1),
+            ("(other)".to_string(), 1),
+        ])
+    );
+    // Already no more than the limit
+    assert_eq!(warnings.directories, TEST_WARNINGS.directories);
+    assert_eq!(warnings.total(), TEST_WARNINGS.total());
+
+    warnings.cap_categories(1);
+    assert_eq!(warnings.keywords.len(), 2);
+    assert_eq!(warnings.keywords["(other)"], 7);
+    assert_eq!(
+        warnings.directories,
+        HashMap::from([
+            (PathBuf::from("/path/to/dir2"), 3),
+            (PathBuf::from("(other)"), 1),
+        ])
+    );
+
+    warnings.recount();
+    assert_eq!(warnings.keywords, TEST_WARNINGS.keywords);
+}
+
+#[test]
+fn retain_warning_names() {
+    let mut warnings = TEST_WARNINGS.clone();
+    warnings.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+
+    assert_eq!(warnings.total(), 2);
+    assert_eq!(
+        warnings.names,
+        HashMap::from([("horrible-stuff".to_string(), 2)])
+    );
+    assert_eq!(
+        warnings.files,
+        HashMap::from([(PathBuf::from("/path/to/dir2/file2.c"), 2)])
+    );
+    assert_eq!(
+        warnings.directories,
+        HashMap::from([(PathBuf::from("/path/to/dir2"), 2)])
+    );
+    assert_eq!(
+        warnings.keywords,
+        HashMap::from([("horrible".to_string(), 2), ("stuff".to_string(), 2)])
+    );
+
+    warnings.retain_names(&HashSet::new());
+    assert_eq!(warnings.total(), 0);
+    assert!(warnings.directories.is_empty());
+}
+
+#[test]
+fn counts_versus_averages() {
+    let mut older = TEST_WARNINGS.clone();
+    older.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    let mut old = TEST_WARNINGS.clone();
+    old.merge(TEST_WARNINGS.clone());
+
+    // horrible-stuff: 2 and 4, bad-thing: 0 and 2
+    let averages = Averages::new(&[older, old]);
+    assert_eq!(averages.0[&Category::Names]["horrible-stuff"], 3.0);
+    assert_eq!(averages.0[&Category::Names]["bad-thing"], 1.0);
+
+    let mut current = TEST_WARNINGS.clone();
+    current.warnings.push(Warning {
+        name: "brand-new".to_string(),
+        ..TEST_WARNINGS.warnings[0].clone()
+    });
+    current.recount();
+    let options = ReportOptions {
+        sections: vec![Category::Names],
+        averages: Some(averages),
+        ..Default::default()
+    };
+    assert_eq!(
+        current.report(&options).to_string(),
+        "Detected: gcc
+
+Warnings:
+2  horrible-stuff  (-1 vs avg 3)
+1  bad-thing  (+0 vs avg 1)
+1  brand-new  (new)
+1  dont-like-this  (+0 vs avg 1)
+5  Total
+"
+    );
+}
+
+#[test]
+fn round_trip_counts() {
+    let counts = TEST_WARNINGS.counts();
+    assert_eq!(counts.names, TEST_WARNINGS.names);
+    assert_eq!(counts.keywords, TEST_WARNINGS.keywords);
+
+    let snapshot = WarningCollection::from_counts(counts);
+    assert!(snapshot.warnings.is_empty());
+    assert_eq!(snapshot.total(), TEST_WARNINGS.total());
+    assert!(snapshot.diff(&TEST_WARNINGS).is_empty());
+    assert!(TEST_WARNINGS.diff(&snapshot).is_empty());
+
+    let mut fewer = TEST_WARNINGS.clone();
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    assert!(!snapshot.diff(&fewer).is_empty());
+}
+
+#[test]
+fn merge_counts_only() {
+    let mut merged = WarningCollection::from_counts(TEST_WARNINGS.counts());
+    merged.merge(WarningCollection::from_counts(TEST_WARNINGS.counts()));
+    assert_eq!(merged.total(), 2 * TEST_WARNINGS.total());
+    assert_eq!(merged.names["horrible-stuff"], 4);
+    assert_eq!(merged.files[&PathBuf::from("/path/to/dir2/file2.c")], 4);
+    assert_eq!(merged.keywords["zimb"], 2 * TEST_WARNINGS.keywords["zimb"]);
+
+    // Warnings merged into counts add to them
+    merged.merge(TEST_WARNINGS.clone());
+    assert_eq!(merged.total(), 3 * TEST_WARNINGS.total());
+    assert_eq!(merged.names["bad-thing"], 3);
+}
+
+#[test]
+fn filter_counts_only() {
+    let mut stripped = WarningCollection::from_counts(TEST_WARNINGS.counts());
+    assert_eq!(stripped.strip_common_prefix(), PathBuf::from("/path/to"));
+    assert_eq!(stripped.files[&PathBuf::from("dir2/file2.c")], 2);
+    assert_eq!(stripped.directories[&PathBuf::from("dir2")], 3);
+    assert_eq!(stripped.total(), TEST_WARNINGS.total());
+
+    let mut fewer = WarningCollection::from_counts(TEST_WARNINGS.counts());
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    assert_eq!(
+        fewer.names,
+        HashMap::from([("horrible-stuff".to_string(), 2)])
+    );
+    assert!(fewer.files.is_empty());
+
+    let mut suppressed = WarningCollection::from_counts(TEST_WARNINGS.counts());
+    assert!(matches!(
+        suppressed.suppress_baseline(&TEST_WARNINGS),
+        Err(Error::CountsOnly(_))
+    ));
+    assert_eq!(suppressed.total(), TEST_WARNINGS.total());
+    let mut warnings = TEST_WARNINGS.clone();
+    assert!(warnings
+        .suppress_baseline(&WarningCollection::from_counts(TEST_WARNINGS.counts()))
+        .is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn round_trip_names_only_counts() {
+    let json = serde_json::to_string(&TEST_WARNINGS.counts().names_only()).unwrap();
+    assert!(json.starts_with(r#"{"names":{"#));
+    assert!(!json.contains("path"));
+
+    let counts: Counts = serde_json::from_str(&json).unwrap();
+    let baseline = WarningCollection::from_counts(counts);
+
+    assert!(TEST_WARNINGS.diff(&baseline).is_empty());
+    assert!(TEST_WARNINGS.diff_ignoring_path_base(&baseline).is_empty());
+
+    let mut fewer = TEST_WARNINGS.clone();
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    let diff = fewer.diff(&baseline);
+    assert_eq!(
+        diff.names,
+        HashMap::from([
+            ("bad-thing".to_string(), -1),
+            ("dont-like-this".to_string(), -1),
+        ])
+    );
+    assert!(diff.files.is_empty());
+    assert!(diff.keywords.is_empty());
+}
+
+#[test]
+fn symmetric_difference_of_collections() {
+    let old = WarningCollection::new(
+        "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:340:27: warning: don't like this [-Wdont-like-this]
+",
+        5,
+        &[] as &[String],
+    );
+    let new = WarningCollection::new(
+        "/path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/dir2/file1.c:341:27: warning: don't like this [-Wdont-like-this]
+/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+        5,
+        &[] as &[String],
+    );
+
+    let (fixed, added) = old.symmetric_difference(&new);
+    assert_eq!(fixed, [&old.warnings[1]]);
+    assert_eq!(added, [&new.warnings[1], &new.warnings[2]]);
+
+    let (fixed, added) = old.symmetric_difference(&old);
+    assert!(fixed.is_empty());
+    assert!(added.is_empty());
+}
+
+#[test]
+fn suppress_warnings_in_baseline() {
+    let mut baseline = TEST_WARNINGS.clone();
+    baseline.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    // Same name and file as a current warning, but a different line
+    baseline.warnings[1].line = Some(1);
+
+    let mut warnings = TEST_WARNINGS.clone();
+    warnings.suppress_baseline(&baseline).unwrap();
+    assert_eq!(warnings.total(), 3);
+    assert_eq!(
+        warnings.names,
+        HashMap::from([
+            ("bad-thing".to_string(), 1),
+            ("dont-like-this".to_string(), 1),
+            ("horrible-stuff".to_string(), 1),
+        ])
+    );
+    assert_eq!(warnings.warnings[2].line, Some(715));
+}
+
+#[test]
+fn keywords_from_underlined_token_after_tab() {
+    let log = "\
+/path/to/dir1/file1.c:10:13: warning: unused variable [-Wunused-variable]
+   10 | \tint pointer = other_value;
+      |             ^~~~~~~
+";
+    let options = ParseOptions {
+        keyword_underlined_only: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.warnings[0].keywords, vec_of_strings!["pointer"]);
+
+    assert_eq!(expand_tabs("   10 | \tint\tx;", 4), "   10 |     int x;");
+}
+
+#[test]
+fn warning_signatures() {
+    let log = "\
+/path/to/dir1/file1.c:12:9: warning: unused variable 'count' [-Wunused-variable]
+/path/to/dir2/file2.c:40:3: warning: unused variable 'total' [-Wunused-variable]
+/path/to/dir2/file2.c:41:3: warning: comparison of integer expressions of different signedness: 'int' and 'size_t' [-Wsign-compare]
+/path/to/dir1/file1.c(7): warning #177: variable \"x\" was declared but never referenced
+";
+    let options = ParseOptions {
+        keep_messages: true,
+        ..Default::default()
+    };
+    let collection = WarningCollection::with_options(log, &options);
+    assert_eq!(
+        collection.warnings[0].message.as_deref(),
+        Some("unused variable 'count'")
+    );
+    assert_eq!(
+        collection.display_counts(Category::Signatures),
+        HashMap::from([
+            ("unused-variable: unused variable '...'".to_string(), 2),
+            (
+                "sign-compare: comparison of integer expressions of different signedness: '...' and '...'"
+                    .to_string(),
+                1
+            ),
+            ("177: variable '...' was declared but never referenced".to_string(), 1),
+        ])
+    );
+
+    let options = ReportOptions {
+        sections: vec![Category::Names],
+        signatures: true,
+        ..Default::default()
+    };
+    let report = collection.report(&options).to_string();
+    assert!(report.contains("3 distinct signatures.\n"));
+    assert!(report.contains("Signatures:\n2  unused-variable: unused variable '...'\n"));
+
+    // Without the messages, signatures are just names
+    let collection = WarningCollection::new(log, 5, &[] as &[String]);
+    assert_eq!(
+        collection.display_counts(Category::Signatures),
+        collection.names
+    );
+}
+
+#[test]
+fn thousands_separators() {
+    assert_eq!(grouped_count(12345, Some(',')), "12,345");
+    assert_eq!(grouped_count(-1234, Some('.')), "-1.234");
+    assert_eq!(grouped_count(999, Some(',')), "999");
+    assert_eq!(grouped_count(12345, None), "12345");
+
+    let counts = HashMap::from([
+        ("/path/to/file1.c".to_string(), 12345),
+        ("/path/to/file2.c".to_string(), 7),
+    ]);
+    assert_eq!(
+        make_section_by(&counts, 0, false, SortOrder::Count, None, Some(',')).to_string(),
+        "\
+12,345  /path/to/file1.c
+     7  /path/to/file2.c
+12,352  Total"
+    );
+    assert_eq!(
+        make_section(&counts, 0, false, SortOrder::Count).to_string(),
+        "\
+12345  /path/to/file1.c
+    7  /path/to/file2.c
+12352  Total"
+    );
+}
+
+#[test]
+fn keyword_themes() {
+    let themes = count_keyword_names(&TEST_WARNINGS.warnings, false);
+    assert_eq!(
+        themes["horrible"],
+        HashMap::from([
+            (String::from("horrible-stuff"), 2),
+            (String::from("bad-thing"), 1)
+        ])
+    );
+
+    assert_eq!(
+        themes_section(
+            &TEST_WARNINGS,
+            make_section(&TEST_WARNINGS.keywords, 2, true, SortOrder::Count),
+            1,
+            None
+        )
+        .to_string(),
+        " 3  horrible: horrible-stuff (2), bad-thing (1)
+ 2  stuff: horrible-stuff (2)
+    (+3 more items)
+ 5  Total"
+    );
+}
+
+#[test]
+fn themes_with_report_options() {
+    let collection = WarningCollection::from_warnings(
+        vec![TEST_WARNINGS.warnings[0].clone(); 1200],
+        &ParseOptions::default(),
+    );
+    let options = ReportOptions {
+        sections: Vec::new(),
+        themes: true,
+        tiebreak: Tiebreak::FirstSeen,
+        thousands_sep: Some(','),
+        ..Default::default()
+    };
+    let report = collection.report(&options).to_string();
+    assert!(report.contains("Themes:\n1,200  horrible: bad-thing (1,200)\n1,200  zing:"));
+}
+
+#[test]
+fn distribution_of_files() {
+    let files = [1, 1, 1, 2, 5, 6, 12, 70]
+        .into_iter()
+        .enumerate()
+        .map(|(index, count)| (PathBuf::from(format!("file{index}.c")), count))
+        .collect::<HashMap<_, _>>();
+    assert_eq!(
+        distribution_section(&files, &Buckets::default()).to_string(),
+        "\
+3  1
+2  2-5
+1  6-10
+1  11-50
+1  51+
+8  Total"
+    );
+
+    let buckets = "1-2, 3+".parse::<Buckets>().unwrap();
+    assert_eq!(
+        distribution_section(&files, &buckets).items,
+        vec![("1-2".to_string(), 4), ("3+".to_string(), 4)]
+    );
+    assert_eq!(
+        distribution_section(&files, &"1,0".parse().unwrap()).items[1].1,
+        0
+    );
+
+    assert!(matches!(
+        "1,5-2".parse::<Buckets>(),
+        Err(Error::InvalidBucket(bucket)) if bucket == "5-2"
+    ));
+    assert!("1,x".parse::<Buckets>().is_err());
+}
+
+#[test]
+fn directory_density() {
+    assert_eq!(
+        density_section(&TEST_WARNINGS.warnings, 10).to_string(),
+        "\
+1.5  /path/to/dir2
+1.0  /path/to/dir1
+1.3  Overall"
+    );
+    assert_eq!(
+        density_section(&TEST_WARNINGS.warnings, 1).to_string(),
+        "\
+1.5  /path/to/dir2
+     (+1 more items)
+1.3  Overall"
+    );
+}
+
+#[test]
+fn read_cppcheck_xml() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<results version="2">
+    <cppcheck version="2.13.0"/>
+    <errors>
+        <error id="nullPointer" severity="error" msg="Null pointer dereference: horrible" verbose="Null pointer dereference: horrible" cwe="476" file0="/path/to/dir1/file1.c">
+            <location file="/path/to/dir1/file1.c" line="235" column="36" info="Null pointer dereference"/>
+            <location file="/path/to/dir1/file1.c" line="230" column="5" info="Assignment &apos;horrible=NULL&apos;"/>
+            <symbol>horrible</symbol>
+        </error>
+        <error id="unusedVariable" severity="style" msg="Unused variable: stuff" verbose="Unused variable: stuff" cwe="563" file0="/path/to/dir2/file2.c">
+            <location file="/path/to/dir2/file2.c" line="697" column="16"/>
+        </error>
+        <error id="missingIncludeSystem" severity="information" msg="Include file not found"/>
+    </errors>
+</results>
+"#;
+    let result = WarningCollection::from_cppcheck_xml(xml, &ParseOptions::default()).unwrap();
+    assert_eq!(result.total(), 2);
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["cppcheck"]);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("nullPointer".to_string(), 1),
+            ("unusedVariable".to_string(), 1),
+        ])
+    );
+    assert_eq!(result.warnings[0].line, Some(235));
+    assert_eq!(result.warnings[0].column, Some(36));
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["pointer", "dereference", "horrible"]
+    );
+    assert_eq!(
+        result.files,
+        HashMap::from([
+            (PathBuf::from("/path/to/dir1/file1.c"), 1),
+            (PathBuf::from("/path/to/dir2/file2.c"), 1),
+        ])
+    );
+
+    let version1 = r#"<results>
+<error file="/path/to/dir1/file1.c" line="235" id="nullPointer" severity="error" msg="Null pointer dereference"/>
+</results>"#;
+    let result = WarningCollection::from_cppcheck_xml(version1, &ParseOptions::default()).unwrap();
+    assert_eq!(
+        result.names,
+        HashMap::from([("nullPointer".to_string(), 1)])
+    );
+
+    assert!(WarningCollection::from_cppcheck_xml(
+        "<results><error></results>",
+        &ParseOptions::default()
+    )
+    .is_err());
+}
+
+#[test]
+fn display_warning_diff() {
+    let mut fewer = TEST_WARNINGS.clone();
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    let diff = fewer.diff(&TEST_WARNINGS);
+    assert_eq!(diff.total(), -2);
+    assert_eq!(
+        diff.to_string(),
+        "\
+Warnings:
+-1  bad-thing
+-1  dont-like-this
+
+Files:
+-1  /path/to/dir1/file1.c
+-1  /path/to/dir2/file1.c
+
+Directories:
+-1  /path/to/dir1
+-1  /path/to/dir2
+
+Filenames:
+-2  file1.c
+
+Keywords:
+-2  zimb
+-2  zing
+-1  horrible
+-1  zang
+
+Net: -2"
+    );
+}
+
+#[test]
+fn clean_files_from_manifest() {
+    let manifest = "\
+# All the sources
+/path/to/dir1/file1.c
+/path/to/dir1/clean.c
+
+/path/to/dir1/clean.c
+";
+    assert_eq!(
+        TEST_WARNINGS.clean_files(manifest, &ParseOptions::default()),
+        vec![PathBuf::from("/path/to/dir1/clean.c")]
+    );
+}
+
+#[test]
+fn name_index_matches_linear_scan() {
+    let index = TEST_WARNINGS.name_index();
+    for name in TEST_WARNINGS
+        .names
+        .keys()
+        .map(String::as_str)
+        .chain(["missing"])
+    {
+        let scanned = TEST_WARNINGS
+            .warnings
+            .iter()
+            .enumerate()
+            .filter(|(_, warning)| warning.name == name)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(index.indices(name), scanned);
+        assert!(index.warnings(name).all(|warning| warning.name == name));
+    }
+
+    assert_eq!(index.indices("horrible-stuff"), [2, 3]);
+    assert_eq!(
+        index.keywords_for("horrible-stuff", false),
+        HashMap::from([("horrible".to_string(), 2), ("stuff".to_string(), 2)])
+    );
+}
+
+#[test]
+fn display_diff_as_improvement() {
+    let mut fewer = TEST_WARNINGS.clone();
+    fewer.retain_names(&HashSet::from(["horrible-stuff".to_string()]));
+    fewer.warnings.push(Warning {
+        name: "brand-new".to_string(),
+        ..TEST_WARNINGS.warnings[2].clone()
+    });
+    fewer.recount();
+    let diff = fewer.diff(&TEST_WARNINGS);
+
+    let arithmetic = diff.display(DiffStyle::Arithmetic).to_string();
+    assert_eq!(arithmetic, diff.to_string());
+    assert!(arithmetic.starts_with(
+        "\
+Warnings:
+-1  bad-thing
++1  brand-new
+-1  dont-like-this
+"
+    ));
+    assert!(arithmetic.ends_with("Net: -1"));
+
+    let improvement = diff.display(DiffStyle::Improvement).to_string();
+    assert!(improvement.starts_with(
+        "\
+Warnings:
++1  bad-thing  (fixed)
+-1  brand-new  (new)
++1  dont-like-this  (fixed)
+"
+    ));
+    assert!(improvement.ends_with("Net: +1"));
+}
+
+#[test]
+fn display_detailed_diff() {
+    let mut current = TEST_WARNINGS.clone();
+    current
+        .warnings
+        .retain(|warning| warning.name != "bad-thing");
+    current.warnings.push(TEST_WARNINGS.warnings[2].clone());
+    current.recount();
+    let diff = current.diff_detailed(&TEST_WARNINGS);
+
+    assert_eq!(
+        diff.changes(Category::Names)[1],
+        DetailedChange {
+            key: "horrible-stuff".to_string(),
+            before: 2,
+            after: 3,
+        }
+    );
+    assert!(diff.changes(Category::Lines).is_empty());
+
+    let result = diff.to_string();
+    assert!(result.starts_with(
+        "\
+Warnings:
+bad-thing: 1 \u{2192} 0 (-1)
+horrible-stuff: 2 \u{2192} 3 (+1)
+
+Files:
+"
+    ));
+    assert!(result.ends_with("Net: 4 \u{2192} 4 (+0)"));
+}
+
+#[test]
+fn display_diff_relative_to_baseline() {
+    let mut current = TEST_WARNINGS.clone();
+    current
+        .warnings
+        .retain(|warning| warning.name != "bad-thing");
+    for _ in 0..3 {
+        current.warnings.push(TEST_WARNINGS.warnings[2].clone());
+    }
+    current.warnings.push(Warning {
+        name: "brand-new".to_string(),
+        ..TEST_WARNINGS.warnings[2].clone()
+    });
+    current.recount();
+    let diff = current.diff(&TEST_WARNINGS);
+
+    let relative = diff
+        .display(DiffStyle::Arithmetic)
+        .relative_to(&TEST_WARNINGS)
+        .to_string();
+    assert!(relative.starts_with(
+        "\
+Warnings:
++3  horrible-stuff  (+150%)
+-1  bad-thing  (-100%)
++1  brand-new  (new)
+
+Files:
+"
+    ));
+    assert!(relative.ends_with("Net: +3"));
+
+    let improvement = diff
+        .display(DiffStyle::Improvement)
+        .relative_to(&TEST_WARNINGS)
+        .to_string();
+    assert!(improvement.contains("-1  brand-new  (new)\n"));
+    assert!(improvement.contains("+1  bad-thing  (fixed)  (-100%)\n"));
+}
+
+#[test]
+fn read_linker_warnings() {
+    let log = "\
+/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/usr/bin/ld: warning: libfoo.so.1, needed by libbar.so, not found
+ld: warning: creating DT_TEXTREL in a PIE
+/usr/bin/ld: warning: libfoo.so.1, needed by libbar.so, not found
+";
+    let result = WarningCollection::with_options(log, &ParseOptions::default());
+    assert_eq!(result.total(), 1);
+
+    let options = ParseOptions {
+        include_linker: true,
+        ..Default::default()
     };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(result.names["linker"], 3);
+    assert_eq!(
+        result.files,
+        HashMap::from([(PathBuf::from("/path/to/file1.c"), 1)])
+    );
+    assert_eq!(
+        result.directories,
+        HashMap::from([(PathBuf::from("/path/to"), 1)])
+    );
+    assert_eq!(
+        result.basenames,
+        HashMap::from([(PathBuf::from("file1.c"), 1)])
+    );
+    assert!(!result.keywords.contains_key("libfoo"));
+    assert!(!result.keywords.contains_key("creating"));
+    assert_eq!(
+        result.display_counts(Category::Linker),
+        HashMap::from([
+            ("libfoo.so.1, needed by libbar.so, not found".to_string(), 2),
+            ("creating DT_TEXTREL in a PIE".to_string(), 1),
+        ])
+    );
+
+    let report = result
+        .report(&ReportOptions {
+            linker: true,
+            ..Default::default()
+        })
+        .to_string();
+    assert!(report.contains("Linker:\n2  libfoo.so.1, needed by libbar.so, not found\n"));
 }
 
 #[test]
-fn find_a_warning() {
-    let result = WarningCollection::new(
-        "Some warnings
-[  1%] Generating file1.c
-[  2%] Generating file2.c
-/path/to/dir1/file1.c: In function ‘func1’:
+fn read_pragma_messages() {
+    let log = "\
 /path/to/dir1/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
-  235 |     if (horrible) *foo = zing->zimb;
-      |                                ^~~~~
-/path/to/dir2/file1.c: In function ‘func2’:
-/path/to/dir2/file1.c:340:27: warning: don't like this [-Wdont-like-this]
-  340 |     zing->zimb &= (~foo.zang);
-      |                     ^~
-/path/to/dir2/file2.c: In function ‘func3’:
-/path/to/dir2/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
-  697 |     horrible = stuff;
-      |                ^~~
-/path/to/dir2/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
-  715 |       horrible = stuff[i];
-      |                  ^~~
-",
-        3,
-        &["foo"],
+/path/to/dir1/file1.c:3:9: note: '#pragma message: TODO: remove horrible stuff'
+/path/to/dir2/file2.c:7:9: note: #pragma message: TODO: remove horrible stuff
+/path/to/dir2/file2.c:9:9: note: in expansion of macro 'STUFF'
+";
+    let options = ParseOptions {
+        include_pragmas: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("bad-thing".to_string(), 1),
+            (
+                "#pragma message: TODO: remove horrible stuff".to_string(),
+                2
+            ),
+        ])
+    );
+    assert_eq!(result.warnings[1].line, Some(3));
+    assert_eq!(
+        result.warnings[1].keywords,
+        vec_of_strings!["remove", "horrible", "stuff"]
     );
 
-    assert_eq!(result, *TEST_WARNINGS);
-    assert_eq!(result.names, TEST_WARNINGS.names);
-    assert_eq!(result.files, TEST_WARNINGS.files);
-    assert_eq!(result.directories, TEST_WARNINGS.directories);
-    assert_eq!(result.keywords, TEST_WARNINGS.keywords);
+    let result = WarningCollection::with_options(log, &ParseOptions::default());
+    assert_eq!(result.total(), 1);
 }
 
 #[test]
-fn find_a_warning_fortran() {
-    let result = WarningCollection::new(
-        "Some warnings
-[  1%] Generating file1.c
-[  2%] Generating file2.c
-/path/to/dir1/file1.c:235:36:
+fn collapse_nested_templates() {
+    assert_eq!(
+        collapse_templates("std::vector<std::pair<int, std::map<K, V>>> horrible;"),
+        "std::vector<...> horrible;"
+    );
+    assert_eq!(
+        collapse_templates("if (zing < zimb) foo<bar>(x);"),
+        "if (zing < zimb) foo<...>(x);"
+    );
 
-  235 |     if (horrible) *foo = zing->zimb;
-      |                                1
-Warning: doing some bad thing [-Wbad-thing]
-/path/to/dir2/file1.c:340:27:
+    let log = "\
+/path/to/dir1/file1.cpp:12:5: warning: unused variable [-Wunused-variable]
+   12 |   std::vector<std::pair<Horrible, std::map<Stuff, Thing>>> items;
+      |                                                            ^~~~~
+";
+    let options = ParseOptions {
+        collapse_templates: true,
+        ..Default::default()
+    };
+    let result = WarningCollection::with_options(log, &options);
+    assert_eq!(
+        result.warnings[0].keywords,
+        vec_of_strings!["vector", "items"]
+    );
+}
 
-  340 |     zing->zimb &= (~foo.zang);
-      |                     ^
-Warning: don't like this [-Wdont-like-this]
-/path/to/dir2/file2.c:697:16:
+#[test]
+fn directory_tree() {
+    let warning = |file: &str| Warning {
+        file: PathBuf::from(file),
+        ..TEST_WARNINGS.warnings[0].clone()
+    };
+    let warnings = [
+        warning("src/net/tcp/socket.c"),
+        warning("src/net/tcp/socket.c"),
+        warning("src/net/udp.c"),
+        warning("src/main.c"),
+        warning("tests/test.c"),
+    ];
+    assert_eq!(
+        tree_section(&warnings, 1).to_string(),
+        "\
+4  src
+3    net
+2      tcp
+1  tests
+5  Total"
+    );
+}
 
-  697 |     horrible = stuff;
-      |                ^~~
-Warning: just horrible stuff [-Whorrible-stuff]
-/path/to/dir2/file2.c:715:18:
+#[test]
+fn warnings_promoted_to_errors() {
+    let result = WarningCollection::new(
+        "\
+/path/to/dir1/file1.c:235:36: error: format '%d' expects argument of type 'int' [-Werror=format]
+/path/to/dir1/file1.c:240:3: error: unused variable 'zing' [-Werror=unused-variable]
+/path/to/dir1/file1.c:250:3: error: 'zimb' undeclared (first use in this function)
+/path/to/dir2/file2.f90:10:5:
 
-  715 |       horrible = stuff[i];
-      |                  ^~~
-Warning: just horrible stuff [-Whorrible-stuff]
+   10 |     horrible = stuff
+      |     1
+Error: Possible change of value in conversion [-Werror=conversion]
+cc1: all warnings being treated as errors
 ",
-        3,
-        &["foo"],
+        5,
+        &[""],
+    );
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("format".to_string(), 1),
+            ("unused-variable".to_string(), 1),
+            ("conversion".to_string(), 1),
+        ])
+    );
+    assert_eq!(
+        result.detected_toolchains(),
+        vec_of_strings!["gcc", "gfortran"]
     );
-
-    assert_eq!(result, *TEST_WARNINGS);
-    assert_eq!(result.names, TEST_WARNINGS.names);
-    assert_eq!(result.files, TEST_WARNINGS.files);
-    assert_eq!(result.directories, TEST_WARNINGS.directories);
-    assert_eq!(result.keywords, TEST_WARNINGS.keywords);
 }
 
 #[test]
-fn format_hash_map_for_warnings() {
-    let counts = HashMap::from([
-        ("result1".to_string(), 3),
-        ("result2".to_string(), 120),
-        ("result3".to_string(), 1),
-    ]);
+fn diff_with_different_path_bases() {
+    let mut relative = TEST_WARNINGS.clone();
+    for warning in &mut relative.warnings {
+        warning.file = warning.file.strip_prefix("/path/to").unwrap().to_path_buf();
+    }
+    relative.recount();
 
-    let result = make_warning_counts(&counts, 2, false);
-    let expected = "120  result2\n  3  result1\n     (+1 more items)\n124  Total".to_string();
-    assert_eq!(result, expected);
+    assert_eq!(TEST_WARNINGS.common_prefix(), PathBuf::from("/path/to"));
+    assert_ne!(relative.diff(&TEST_WARNINGS).files, HashMap::new());
+
+    let diff = relative.diff_ignoring_path_base(&TEST_WARNINGS);
+    assert_eq!(diff, relative.diff(&relative));
+    assert_eq!(diff.to_string(), "Net: +0");
 }
 
 #[test]
-fn format_hash_map_for_files() {
-    let counts = HashMap::from([
-        ("result1".to_string(), 3),
-        ("result2".to_string(), 120),
-        ("result3".to_string(), 1),
-    ]);
+fn diff_with_different_directories() {
+    let collection = |files: &[&str]| {
+        let mut collection = TEST_WARNINGS.clone();
+        collection.warnings = files
+            .iter()
+            .map(|file| Warning {
+                file: PathBuf::from(file),
+                ..TEST_WARNINGS.warnings[0].clone()
+            })
+            .collect();
+        collection.recount();
+        collection
+    };
+    let baseline = collection(&["src/a/x.c", "src/b/y.c"]);
+    let current = collection(&["src/a/x.c"]);
 
-    let result = make_warning_counts(&counts, 2, true);
-    let expected = "120  result2\n  3  result1\n     (+1 more items)\n  3  Total".to_string();
-    assert_eq!(result, expected);
+    let diff = current.diff_ignoring_path_base(&baseline);
+    assert_eq!(diff.files, HashMap::from([(PathBuf::from("b/y.c"), -1)]));
+    assert_eq!(diff.directories, HashMap::from([(PathBuf::from("b"), -1)]));
+
+    let absolute = collection(&["/home/ci/build/src/a/x.c"]);
+    assert_eq!(absolute.diff_ignoring_path_base(&baseline), diff);
+    assert_eq!(
+        baseline.diff_ignoring_path_base(&absolute).files,
+        HashMap::from([(PathBuf::from("b/y.c"), 1)])
+    );
 }
 
 #[test]
-fn warning_diff() {
-    let new_warnings = WarningCollection {
-        warnings: Vec::from([
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir1/file1.c"),
-                name: String::from("bad-thing"),
-                keywords: vec_of_strings!["horrible", "zing", "zimb"],
-            },
-            Warning {
-                file: std::path::PathBuf::from("/path/to/dir2/file1.c"),
-                name: String::from("dont-like-this"),
-                keywords: vec_of_strings!["zing", "zimb", "zang"],
-            },
-        ]),
-        names: HashMap::from([
-            ("bad-thing".to_string(), 1),
+fn diff_only_new_names() {
+    let mut baseline = TEST_WARNINGS.clone();
+    baseline.retain_names(&HashSet::from([
+        "bad-thing".to_string(),
+        "horrible-stuff".to_string(),
+    ]));
+    baseline.warnings.remove(2);
+    baseline.recount();
+
+    // dont-like-this is new, horrible-stuff has gone from one to two
+    let diff = TEST_WARNINGS.diff(&baseline);
+    assert_eq!(
+        diff.names,
+        HashMap::from([
             ("dont-like-this".to_string(), 1),
-        ]),
-        files: HashMap::from([
-            (PathBuf::from("/path/to/dir1/file1.c"), 1),
-            (PathBuf::from("/path/to/dir2/file1.c"), 1),
-        ]),
-        directories: HashMap::from([
-            (PathBuf::from("/path/to/dir1"), 1),
-            (PathBuf::from("/path/to/dir2"), 1),
-        ]),
-        keywords: HashMap::from([
-            ("horrible".to_string(), 1),
-            ("zang".to_string(), 1),
-            ("zimb".to_string(), 2),
-            ("zing".to_string(), 2),
-        ]),
-    };
-    let result = new_warnings.diff(&TEST_WARNINGS);
+            ("horrible-stuff".to_string(), 1),
+        ])
+    );
+    let diff = diff.only_new_names(&baseline);
+    assert_eq!(
+        diff.names,
+        HashMap::from([("dont-like-this".to_string(), 1)])
+    );
+    assert_eq!(diff.to_string(), "Warnings:\n+1  dont-like-this\n\nNet: +1");
+}
 
-    let expected = WarningCollectionDiff {
-        names: HashMap::from([("horrible-stuff".to_string(), -2)]),
-        files: HashMap::from([(PathBuf::from("/path/to/dir2/file2.c"), -2)]),
-        directories: HashMap::from([(PathBuf::from("/path/to/dir2"), -2)]),
-        keywords: HashMap::from([("horrible".to_string(), -2), ("stuff".to_string(), -2)]),
-    };
+#[test]
+fn read_latex_warnings() {
+    let log = r"This is pdfTeX, Version 3.141592653-2.6-1.40.25 (TeX Live 2023) (preloaded format=pdflatex)
+(./main.tex
+LaTeX2e <2022-11-01> patch level 1
+(/usr/share/texlive/texmf-dist/tex/latex/base/article.cls
+Document Class: article 2022/07/02 v1.4n Standard LaTeX document class
+(/usr/share/texlive/texmf-dist/tex/latex/base/size10.clo))
+(/usr/share/texlive/texmf-dist/tex/latex/hyperref/hyperref.sty)
+(./chapter.tex
+Overfull \hbox (12.3pt too wide) in paragraph at lines 10--12
+[]\OT1/cmr/m/n/10 horrible stuff
+LaTeX Warning: Reference `fig:zing' on page 1 undefined on input line 15.
 
-    assert_eq!(result, expected);
+)
+Package hyperref Warning: Token not allowed in a PDF string (Unicode):
+(hyperref)                removing `math shift' on input line 20.
+
+LaTeX Font Warning: Font shape `OT1/cmr/bx/sc' undefined
+)
+";
+    let result = WarningCollection::new(log, 5, &[""]);
+    assert_eq!(result.detected_toolchains(), vec_of_strings!["latex"]);
+    assert_eq!(
+        result.names,
+        HashMap::from([
+            ("Overfull-hbox".to_string(), 1),
+            ("LaTeX".to_string(), 1),
+            ("Package-hyperref".to_string(), 1),
+            ("LaTeX-Font".to_string(), 1),
+        ])
+    );
+    assert_eq!(
+        result.files,
+        HashMap::from([
+            (PathBuf::from("./chapter.tex"), 2),
+            (PathBuf::from("./main.tex"), 2),
+        ])
+    );
+    assert_eq!(result.warnings[0].line, Some(10));
+    assert_eq!(result.warnings[1].line, Some(15));
 }
@@ -15,14 +15,48 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use warnsum::WarningCollection;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+#[cfg(feature = "tui")]
+mod tui;
+
+use warnsum::{
+    check_mapping, first_lines, last_lines, Aliases, Averages, Buckets, Budget, Category, Crosstab,
+    CustomParser, DiffStyle, DryRun, KeywordLengths, KeywordSource, LineRange, LineTemplate,
+    ParseOptions, Profile, ReportOptions, SortOrder, Tiebreak, Trend, WarningCollection, Weights,
+};
 
 /// Summarise compiler warnings from log file
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    /// Path to log file
-    path: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paths or URLs of log files, which are summarised together
+    #[arg(required_unless_present = "scan")]
+    paths: Vec<PathBuf>,
+
+    /// Also summarise every file under DIR matching `--pattern`, however
+    /// deep
+    #[arg(long, value_name = "DIR")]
+    scan: Vec<PathBuf>,
+
+    /// Names of the files to summarise with `--scan`, with `*` and `?`
+    /// wildcards
+    #[arg(long, value_name = "GLOB", default_value = "*.log", requires = "scan")]
+    pattern: glob::Pattern,
+
+    /// Stop at files found by `--scan` that can't be read or parsed,
+    /// instead of skipping them
+    #[arg(long, requires = "scan")]
+    strict: bool,
+
+    /// Decompress the inputs with gzip
+    #[arg(short = 'z', long)]
+    decompress: bool,
 
     /// Top N items to display in each category
     #[arg(short = 'n', default_value_t = 10)]
@@ -35,20 +69,1461 @@ struct Cli {
     /// Keywords to ignore from warnings
     #[arg(short, long, num_args = 1.., value_delimiter = ' ')]
     ignore: Vec<String>,
+
+    /// Drop warnings with these names entirely, before counting. Names may
+    /// contain `*` wildcards, such as `deprecated-*`
+    #[arg(long, value_name = "NAME", num_args = 1..)]
+    ignore_warning: Vec<String>,
+
+    /// Drop warnings on lines START to END of FILE, such as generated code.
+    /// FILE matches any path ending with it
+    #[arg(long, value_name = "FILE:START-END")]
+    exclude_range: Vec<LineRange>,
+
+    /// File of `FROM=TO` lines, counting warnings called `FROM` as `TO`
+    /// instead, for lints spelt differently by different compilers
+    #[arg(long, value_name = "FILE", global = true)]
+    alias: Option<PathBuf>,
+
+    /// Extra regex for a bespoke warning format. Must contain the named
+    /// groups `file` and `name`, and may contain `line`, `col`, and `text`
+    #[arg(long, value_name = "REGEX", global = true)]
+    custom_regex: Vec<CustomParser>,
+
+    /// Prefix to strip from the names matched by `--custom-regex`, or
+    /// nothing if empty
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "-W",
+        allow_hyphen_values = true
+    )]
+    custom_flag_prefix: String,
+
+    /// Allow keywords to start with a digit, such as `0xDEADBEEF`
+    #[arg(long)]
+    keyword_include_numeric: bool,
+
+    /// Which text of each warning to find keywords in
+    #[arg(long, value_enum, default_value_t = KeywordSourceArg::Code)]
+    keyword_source: KeywordSourceArg,
+
+    /// Only take keywords from the part of the source line underlined by
+    /// the compiler, such as `^~~~`, where there is one
+    #[arg(long)]
+    keyword_underlined_only: bool,
+
+    /// Also count `#pragma message` notes, named after their messages
+    #[arg(long)]
+    include_pragmas: bool,
+
+    /// Also count warnings from linkers, such as `ld: warning: ...`, and
+    /// show them by message in a "Linker:" section
+    #[arg(long)]
+    include_linker: bool,
+
+    /// Count the keywords in `note:` lines, such as type names and
+    /// suggestions, as part of the warning before them
+    #[arg(long)]
+    include_notes_keywords: bool,
+
+    /// Join warnings that were hard-wrapped onto several lines, such as by
+    /// a terminal, back onto one line before parsing
+    #[arg(long)]
+    unwrap: bool,
+
+    /// Number of words from the message to name warnings without a flag
+    /// after, such as Swift's
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    message_name_words: usize,
+
+    /// Number of columns between tab stops that the compiler assumed when
+    /// underlining source lines
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    tab_width: usize,
+
+    /// Replace C++ template arguments with `<...>` before finding keywords,
+    /// so that long template types don't swamp the keywords
+    #[arg(long)]
+    collapse_templates: bool,
+
+    /// Stop parsing each input once it has warnings in N different files,
+    /// to guard against corrupted logs
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Stop parsing each input after its first N warnings, in log order
+    #[arg(long, value_name = "N")]
+    max_warnings_parsed: Option<usize>,
+
+    /// Only keep every Nth warning, given as `1/N`, and scale the counts
+    /// back up, for a quick estimate from a very large log
+    #[arg(long, value_name = "1/N", value_parser = parse_sample)]
+    sample: Option<usize>,
+
+    /// Count each keyword at most once per warning
+    #[arg(long)]
+    unique_keywords_per_warning: bool,
+
+    /// Merge keywords differing only in case when counting them
+    #[arg(long)]
+    fold_keyword_case: bool,
+
+    /// Resolve paths to files that exist, so relative and absolute
+    /// references to the same file are counted together
+    #[arg(long)]
+    canonicalize_existing: bool,
+
+    /// Order of the "Warnings:" section
+    #[arg(long, value_enum, default_value_t = NamesSort::Count)]
+    names_sort: NamesSort,
+
+    /// Order of the "Directories:" section
+    #[arg(long, value_enum, default_value_t = DirsSort::Count)]
+    dirs_sort: DirsSort,
+
+    /// Order of items with the same count
+    #[arg(long, value_enum, default_value_t = TiebreakArg::Alpha)]
+    tiebreak: TiebreakArg,
+
+    /// Group the thousands of counts in the text summary with CHAR, such
+    /// as `,` for `12,345`
+    #[arg(long, value_name = "CHAR")]
+    thousands_sep: Option<char>,
+
+    /// Prefix each line of the "Warnings:" section with its rank
+    #[arg(long)]
+    ranked: bool,
+
+    /// Format for each line of the sections, with `{count}`, `{key}`,
+    /// `{percent}`, `{rank}`, and `{bar}` replaced by their values for the
+    /// item, such as "{count} {key} ({percent}%)"
+    #[arg(long, value_name = "TEMPLATE")]
+    line_template: Option<LineTemplate>,
+
+    /// Count each warning once per file in the "Warnings:" and "Files:"
+    /// sections, showing how widespread warnings are rather than how many
+    #[arg(long)]
+    collapse_repeats: bool,
+
+    /// Show paths relative to the longest directory they're all in, which
+    /// is shown once at the top
+    #[arg(long)]
+    strip_common_prefix: bool,
+
+    /// Show how many distinct items there are in each category
+    #[arg(long)]
+    stats: bool,
+
+    /// Show directories scored by warnings times distinct files
+    #[arg(long)]
+    hotspots: bool,
+
+    /// Show directories by warnings per distinct file, to compare
+    /// directories of different sizes
+    #[arg(long)]
+    density: bool,
+
+    /// Show directories as a tree, with the number of warnings in each
+    /// directory and everything under it
+    #[arg(long)]
+    tree: bool,
+
+    /// Show the most common keywords with the warnings they come up in
+    /// most, hinting at themes running through the warnings
+    #[arg(long)]
+    themes: bool,
+
+    /// Show how many distinct problems there are, counting warnings with
+    /// the same name and message as one, wherever they are
+    #[arg(long)]
+    dedup_across_files: bool,
+
+    /// File of `NAME=WEIGHT` lines, used to score files by the weights of
+    /// their warnings. Unlisted warnings have a weight of 1
+    #[arg(long, value_name = "FILE", global = true)]
+    weights: Option<PathBuf>,
+
+    /// Show how many files have numbers of warnings in each of the
+    /// comma-separated BUCKETS, each `N`, `N-M`, or `N+`
+    #[arg(
+        long,
+        value_name = "BUCKETS",
+        num_args = 0..=1,
+        default_missing_value = "1,2-5,6-10,11-50,51+",
+        require_equals = true
+    )]
+    distribution: Option<Buckets>,
+
+    /// Categories to show, out of names, files, directories, filenames,
+    /// lines, keywords, hotspots, density, tree, scores, themes,
+    /// distribution, signatures, and linker
+    #[arg(
+        long,
+        value_name = "CATEGORY",
+        value_delimiter = ',',
+        default_value = "names,files,directories,keywords"
+    )]
+    show: Vec<Category>,
+
+    /// Order to show the sections in, such as "files,names". Any shown
+    /// sections not listed come after, in the usual order
+    #[arg(long, value_name = "CATEGORY", value_delimiter = ',')]
+    order: Vec<Category>,
+
+    /// Show only the items in one category, without headings, for piping
+    /// into other tools
+    #[arg(long, value_name = "CATEGORY")]
+    only: Option<Category>,
+
+    /// Leave out the headings and blank lines, leaving each section ended
+    /// by its total
+    #[arg(long)]
+    no_headers: bool,
+
+    /// Browse the warnings interactively in the terminal, filtering by
+    /// warning name. Requires the `tui` feature
+    #[arg(long)]
+    tui: bool,
+
+    /// Summarise the total and the top item of each category on a single
+    /// line, for status badges or chat messages
+    #[arg(long, conflicts_with_all = ["only", "no_headers"])]
+    oneline: bool,
+
+    /// Include the total with `--only`
+    #[arg(long, requires = "only")]
+    with_total: bool,
+
+    /// Truncate long items in the middle so that lines are at most N
+    /// characters wide. Defaults to the terminal width, or unlimited when
+    /// not writing to a terminal. Zero means unlimited
+    #[arg(long, value_name = "N")]
+    max_width: Option<usize>,
+
+    /// Fail if there are more than N warnings in total, or more than N
+    /// warnings called NAME
+    #[arg(long, value_name = "[NAME=]N")]
+    max_warnings: Vec<Budget>,
+
+    /// Fail if any warnings with these names are present. Names may contain
+    /// `*` wildcards, such as `unused-*`
+    #[arg(long, value_name = "NAME", num_args = 1..)]
+    fail_on: Vec<String>,
+
+    /// Show how the number of warnings has changed over the snapshots
+    /// matching GLOB, which are either logs or JSON summaries, followed by
+    /// the current input
+    #[arg(long, value_name = "GLOB")]
+    trend: Option<String>,
+
+    /// Show the first warning called NAME in full, as it appeared in the log
+    #[arg(long, value_name = "NAME")]
+    explain: Option<String>,
+
+    /// Show the source of FILE, as it's named in the summary, with a marker
+    /// after each line for each of its warnings
+    #[arg(long, value_name = "FILE")]
+    annotate: Option<PathBuf>,
+
+    /// Directory to read relative source files from with `--annotate`
+    #[arg(long, value_name = "DIR", default_value = ".", requires = "annotate")]
+    root: PathBuf,
+
+    /// Show the files listed in MANIFEST, one per line, that have no
+    /// warnings
+    #[arg(long, value_name = "MANIFEST")]
+    all_files: Option<PathBuf>,
+
+    /// Only show the number of files without warnings with `--all-files`
+    #[arg(long, requires = "all_files")]
+    clean_count: bool,
+
+    /// Show how the counts have changed since the baseline FILE, a log or
+    /// JSON summary
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Save just the number of each warning to FILE as JSON, without any
+    /// paths or keywords, as a small baseline that's safe to share.
+    /// Diffing against it only compares the warnings
+    #[arg(long, value_name = "FILE")]
+    save_baseline_counts: Option<PathBuf>,
+
+    /// Compare files with `--baseline` by the end of their paths, ignoring
+    /// the directories they're under, for logs with different path bases
+    #[arg(long, requires = "baseline")]
+    ignore_path_base: bool,
+
+    /// Only show warning names that aren't in the baseline at all
+    #[arg(long, requires = "baseline")]
+    only_new_names: bool,
+
+    /// Also show the changes in warning names as percentages of their
+    /// counts in the baseline
+    #[arg(long, alias = "relative-counts", requires = "baseline")]
+    relative: bool,
+
+    /// Show the counts in the baseline and now alongside each change, as
+    /// `old → new (change)`
+    #[arg(
+        long,
+        requires = "baseline",
+        conflicts_with_all = ["ignore_path_base", "only_new_names", "relative"]
+    )]
+    diff_detailed: bool,
+
+    /// Only list the files whose number of warnings has changed since
+    /// `--baseline`, one per line
+    #[arg(long, requires = "baseline")]
+    changed_files: bool,
+
+    /// Follow each file from `--changed-files` with a tab and its change
+    #[arg(long, requires = "changed_files")]
+    with_delta: bool,
+
+    /// Format of the changes shown with `--baseline`
+    #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+    diff_format: DiffFormat,
+
+    /// How to show the changes with `--baseline`
+    #[arg(long, value_enum, default_value_t = DiffStyleArg::Arithmetic)]
+    diff_style: DiffStyleArg,
+
+    /// Leave out warnings already in the baseline FILE, a log or JSON
+    /// summary, matching them by name, file, and line. Neither side can be
+    /// just counts, as from `--save-baseline-counts`. The budgets only
+    /// count the warnings that are left
+    #[arg(long, value_name = "FILE")]
+    suppress_baseline: Option<PathBuf>,
+
+    /// Show how far each count is from its average over the snapshots
+    /// matching GLOB, which are either logs or JSON summaries
+    #[arg(long, value_name = "GLOB")]
+    avg_baseline: Option<String>,
+
+    /// Show how often the top keywords appear in each of the top N warning
+    /// names, as a table. Wide tables may exceed the terminal width
+    #[arg(long)]
+    crosstab: bool,
+
+    /// Number of keywords to show with `--crosstab`, or all of them if zero
+    #[arg(long, value_name = "M", default_value_t = 5, requires = "crosstab")]
+    crosstab_keywords: usize,
+
+    /// Show how many distinct keywords there are of each length, and how
+    /// often they come up, to help pick `--keyword-len`
+    #[arg(long)]
+    keyword_length_histogram: bool,
+
+    /// Only check which parsers match the log, showing a few sample warnings
+    #[arg(long, conflicts_with_all = ["max_warnings", "fail_on", "exit_codes", "warn_if_empty"])]
+    dry_run: bool,
+
+    /// Exit with 0 if there are no warnings, 2 if there are but they're
+    /// within the budgets, 3 if they're over budget, banned, or have
+    /// increased since `--baseline`, or 4 if `--warn-if-empty` found
+    /// nothing. Errors always exit with 1
+    #[arg(long)]
+    exit_codes: bool,
+
+    /// Print a notice and fail if no warnings were found in inputs that
+    /// weren't empty, which usually means the logs are in a format warnsum
+    /// doesn't recognise
+    #[arg(long)]
+    warn_if_empty: bool,
+
+    /// Format of the input file
+    #[arg(long, value_enum, default_value_t = InputFormat::Log)]
+    input_format: InputFormat,
+
+    /// Trust the counts in a JSON input rather than recomputing them
+    #[arg(long)]
+    trust_maps: bool,
+
+    /// Read each log on a separate thread, parsing it as it arrives rather
+    /// than after it has all been read
+    #[arg(long)]
+    stream: bool,
+
+    /// Only parse the first N lines of each log, and the rest of the
+    /// warning the last line is part of
+    #[arg(long, value_name = "N", conflicts_with_all = ["tail", "stream"])]
+    head: Option<usize>,
+
+    /// Only parse the last N lines of each log, and the start of the
+    /// warning the first line is part of
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    tail: Option<usize>,
+
+    /// Don't show progress or notices on stderr
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Show on stderr how long parsing, finding keywords, counting, and
+    /// rendering took
+    #[arg(long)]
+    profile: bool,
+
+    /// Format of the summary. Several formats can be given with
+    /// `--output-dir`
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "text")]
+    format: Vec<Format>,
+
+    /// Character to separate fields with in `--format csv`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_delimiter)]
+    delimiter: u8,
+
+    /// Start the summary with when and where it was made, and from which
+    /// inputs. Included in the JSON output as `meta`
+    #[arg(long)]
+    metadata: bool,
+
+    /// Write the summary in each `--format` to DIR, as `summary.txt`,
+    /// `summary.csv`, `summary.json`, and so on, instead of to stdout
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the `--alias` and `--weights` files and `--custom-regex`
+    /// patterns, without summarising anything. Stops at the first problem
+    CheckConfig,
+}
+
+/// Parse a single ASCII character to separate fields with
+fn parse_delimiter(delimiter: &str) -> Result<u8, String> {
+    match delimiter.as_bytes() {
+        [byte] if byte.is_ascii() && !matches!(byte, b'"' | b'\n' | b'\r') => Ok(*byte),
+        _ => Err(format!(
+            "expected a single ASCII character other than a quote or newline, not `{delimiter}`"
+        )),
+    }
+}
+
+/// Parse a sampling rate like `1/10` into the stride between kept warnings
+fn parse_sample(rate: &str) -> Result<usize, String> {
+    rate.strip_prefix("1/")
+        .and_then(|every| every.parse().ok())
+        .filter(|&every| every > 0)
+        .ok_or_else(|| format!("expected `1/N` for some positive N, not `{rate}`"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum NamesSort {
+    /// Alphabetically by warning name
+    Name,
+    /// Most common first
+    Count,
+}
+
+impl From<NamesSort> for SortOrder {
+    fn from(sort: NamesSort) -> Self {
+        match sort {
+            NamesSort::Name => SortOrder::Key,
+            NamesSort::Count => SortOrder::Count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum DirsSort {
+    /// By path, so related directories are next to each other
+    Path,
+    /// Most common first
+    Count,
+}
+
+impl From<DirsSort> for SortOrder {
+    fn from(sort: DirsSort) -> Self {
+        match sort {
+            DirsSort::Path => SortOrder::Key,
+            DirsSort::Count => SortOrder::Count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum TiebreakArg {
+    /// Alphabetically, or by path for files
+    Alpha,
+    /// In the order they first appear in the log
+    FirstSeen,
+}
+
+impl From<TiebreakArg> for Tiebreak {
+    fn from(tiebreak: TiebreakArg) -> Self {
+        match tiebreak {
+            TiebreakArg::Alpha => Tiebreak::Alpha,
+            TiebreakArg::FirstSeen => Tiebreak::FirstSeen,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum KeywordSourceArg {
+    /// The source line the warning is about, or the message if there isn't
+    /// one
+    Code,
+    /// The message after `warning:`
+    Message,
+    /// Both the source line and the message
+    Both,
+}
+
+impl From<KeywordSourceArg> for KeywordSource {
+    fn from(source: KeywordSourceArg) -> Self {
+        match source {
+            KeywordSourceArg::Code => KeywordSource::Code,
+            KeywordSourceArg::Message => KeywordSource::Message,
+            KeywordSourceArg::Both => KeywordSource::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    /// Compiler output
+    Log,
+    /// Summary previously written with `--format json`
+    #[cfg(feature = "serde")]
+    Json,
+    /// Results written by `cppcheck --xml`
+    CppcheckXml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// Human-readable summary
+    Text,
+    /// Every count, one per row
+    Csv,
+    /// As `csv`, but separated by tabs
+    Tsv,
+    /// Every count as a `category.key=count` line, after the total
+    Kv,
+    /// Every count as a gauge, for Prometheus's textfile collector
+    Prometheus,
+    /// Machine-readable collection of all warnings and counts
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum DiffFormat {
+    /// Human-readable changes
+    Text,
+    /// Machine-readable changes, with the same fields as `--format json`
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum DiffStyleArg {
+    /// Show the change in the number of warnings, so fewer is negative
+    Arithmetic,
+    /// Show fixed warnings as positive and new warnings as negative
+    Improvement,
+}
+
+impl From<DiffStyleArg> for DiffStyle {
+    fn from(style: DiffStyleArg) -> Self {
+        match style {
+            DiffStyleArg::Arithmetic => DiffStyle::Arithmetic,
+            DiffStyleArg::Improvement => DiffStyle::Improvement,
+        }
+    }
+}
+
+impl Format {
+    /// Character separating fields, for the delimited formats
+    fn delimiter(self, args: &Cli) -> u8 {
+        match self {
+            Format::Tsv => b'\t',
+            _ => args.delimiter,
+        }
+    }
+
+    /// Name of the file to write the summary to with `--output-dir`
+    fn filename(self) -> &'static str {
+        match self {
+            Format::Text => "summary.txt",
+            Format::Csv => "summary.csv",
+            Format::Tsv => "summary.tsv",
+            Format::Kv => "summary.kv",
+            Format::Prometheus => "summary.prom",
+            #[cfg(feature = "serde")]
+            Format::Json => "summary.json",
+        }
+    }
+}
+
+/// JSON diff output: the changes in each category, plus the net change
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonDiff<'a> {
+    #[serde(flatten)]
+    diff: &'a warnsum::WarningCollectionDiff,
+
+    total: i32,
+}
+
+/// JSON output: the full collection, plus some derived information
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonReport<'a> {
+    #[serde(flatten)]
+    collection: &'a WarningCollection,
+
+    stats: warnsum::Stats,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<&'a Metadata>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> JsonReport<'a> {
+    fn new(collection: &'a WarningCollection, meta: Option<&'a Metadata>) -> Self {
+        JsonReport {
+            collection,
+            stats: collection.stats(),
+            meta,
+        }
+    }
+}
+
+/// Where and when a summary was made, for `--metadata`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct Metadata {
+    generated: String,
+    host: String,
+    inputs: Vec<InputMetadata>,
 }
 
 #[derive(Debug)]
-struct CustomError(String);
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+struct InputMetadata {
+    path: PathBuf,
+    /// Size of the input, unless it was fetched from a URL
+    bytes: Option<u64>,
+}
+
+impl Metadata {
+    fn new(paths: &[PathBuf]) -> Metadata {
+        Metadata {
+            generated: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+            host: gethostname::gethostname().to_string_lossy().into_owned(),
+            inputs: paths
+                .iter()
+                .map(|path| InputMetadata {
+                    path: path.clone(),
+                    bytes: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Generated: {}", self.generated)?;
+        writeln!(f, "Host: {}", self.host)?;
+        for input in &self.inputs {
+            match input.bytes {
+                Some(bytes) => writeln!(f, "Input: {} ({bytes} bytes)", input.path.display())?,
+                None => writeln!(f, "Input: {}", input.path.display())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse warnings from the log at `path`
+fn parse_log(path: &Path, args: &Cli, options: &ParseOptions) -> Result<WarningCollection> {
+    if args.stream && !is_url(path) {
+        stream_log(path, args.decompress, options)
+    } else if let Some(count) = args.head {
+        let content = read_input(path, args.decompress)?;
+        Ok(parse_content(
+            first_lines(&content, count),
+            options,
+            args.quiet,
+        ))
+    } else if let Some(count) = args.tail {
+        let content = read_input(path, args.decompress)?;
+        Ok(parse_content(
+            last_lines(&content, count),
+            options,
+            args.quiet,
+        ))
+    } else if is_url(path) || args.decompress || show_progress(args.quiet) {
+        let content = read_input(path, args.decompress)?;
+        Ok(parse_content(&content, options, args.quiet))
+    } else {
+        Ok(WarningCollection::from_file(path, options)?)
+    }
+}
+
+/// Parse the log at `path` as it is read, with the reading done on another
+/// thread so that slow storage and parsing overlap
+fn stream_log(path: &Path, decompress: bool, options: &ParseOptions) -> Result<WarningCollection> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("could not read file `{}`", path.display()))?;
+    let mut reader: Box<dyn Read + Send> = if decompress {
+        Box::new(flate2::read::MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    // Bounded, so a fast reader can't fill up memory ahead of the parser
+    let (sender, receiver) = std::sync::mpsc::sync_channel(4);
+    std::thread::spawn(move || loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let result = reader.read(&mut chunk).map(|len| {
+            chunk.truncate(len);
+            chunk
+        });
+        let done = !matches!(&result, Ok(chunk) if !chunk.is_empty());
+        if sender.send(result).is_err() || done {
+            break;
+        }
+    });
+
+    let mut ingest = WarningCollection::ingest(options);
+    // Bytes of a character split between two chunks
+    let mut tail = Vec::new();
+    for chunk in receiver {
+        let chunk = chunk.with_context(|| format!("could not read file `{}`", path.display()))?;
+        if chunk.is_empty() {
+            break;
+        }
+        tail.extend(chunk);
+        let valid = match std::str::from_utf8(&tail) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(_) => anyhow::bail!("`{}` is not valid UTF-8", path.display()),
+        };
+        let rest = tail.split_off(valid);
+        ingest.push(std::str::from_utf8(&tail).expect("prefix should be valid UTF-8"));
+        tail = rest;
+    }
+    if !tail.is_empty() {
+        anyhow::bail!("`{}` is not valid UTF-8", path.display());
+    }
+    Ok(ingest.finish())
+}
+
+#[cfg(feature = "progress")]
+fn show_progress(quiet: bool) -> bool {
+    use std::io::IsTerminal;
+
+    !quiet && std::io::stderr().is_terminal()
+}
+
+#[cfg(not(feature = "progress"))]
+fn show_progress(_quiet: bool) -> bool {
+    false
+}
+
+/// Parse warnings from `content`, showing a progress bar if stderr is a
+/// terminal
+#[cfg(feature = "progress")]
+fn parse_content(content: &str, options: &ParseOptions, quiet: bool) -> WarningCollection {
+    if !show_progress(quiet) {
+        return WarningCollection::with_options(content, options);
+    }
+
+    let bar = indicatif::ProgressBar::new(content.len() as u64).with_style(
+        indicatif::ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes}")
+            .expect("progress bar template should be valid"),
+    );
+    let warnings = WarningCollection::with_progress(content, options, |offset| {
+        bar.set_position(offset as u64)
+    });
+    bar.finish_and_clear();
+    warnings
+}
+
+#[cfg(not(feature = "progress"))]
+fn parse_content(content: &str, options: &ParseOptions, _quiet: bool) -> WarningCollection {
+    WarningCollection::with_options(content, options)
+}
+
+fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Whether `path` is a file with nothing in it. URLs are never counted as
+/// empty, as that would mean fetching them twice
+fn is_empty_file(path: &Path) -> bool {
+    !is_url(path) && std::fs::metadata(path).is_ok_and(|metadata| metadata.len() == 0)
+}
+
+/// Read the file or URL at `path`, optionally decompressing it with gzip
+fn read_input(path: &Path, decompress: bool) -> Result<String> {
+    let bytes = if is_url(path) {
+        fetch(path)?
+    } else {
+        std::fs::read(path).with_context(|| format!("could not read file `{}`", path.display()))?
+    };
+
+    let bytes = if decompress {
+        let mut decompressed = Vec::new();
+        flate2::read::MultiGzDecoder::new(&bytes[..])
+            .read_to_end(&mut decompressed)
+            .with_context(|| format!("could not decompress `{}`", path.display()))?;
+        decompressed
+    } else {
+        bytes
+    };
+
+    String::from_utf8(bytes).with_context(|| format!("`{}` is not valid UTF-8", path.display()))
+}
+
+#[cfg(feature = "http")]
+fn fetch(url: &Path) -> Result<Vec<u8>> {
+    let url = url.display().to_string();
+    let response = reqwest::blocking::get(&url)
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("could not fetch `{url}`"))?;
+    let bytes = response
+        .bytes()
+        .with_context(|| format!("could not read response from `{url}`"))?;
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &Path) -> Result<Vec<u8>> {
+    anyhow::bail!(
+        "could not fetch `{}`: warnsum was built without the `http` feature",
+        url.display()
+    )
+}
+
+/// Write the counts of names in `warnings` to `path`, as JSON
+#[cfg(feature = "serde")]
+fn save_counts(path: &Path, warnings: &WarningCollection) -> Result<()> {
+    let json = serde_json::to_string_pretty(&warnings.counts().names_only())?;
+    std::fs::write(path, json + "\n")
+        .with_context(|| format!("could not write file `{}`", path.display()))
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_counts(_path: &Path, _warnings: &WarningCollection) -> Result<()> {
+    anyhow::bail!("could not save counts: warnsum was built without the `serde` feature")
+}
+
+#[cfg(feature = "tui")]
+fn browse(warnings: &WarningCollection) -> Result<()> {
+    tui::run(warnings)
+}
+
+#[cfg(not(feature = "tui"))]
+fn browse(_warnings: &WarningCollection) -> Result<()> {
+    anyhow::bail!("could not start the browser: warnsum was built without the `tui` feature")
+}
+
+/// Width of the terminal, if writing to one
+fn terminal_width() -> Option<usize> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Guess the format of a snapshot from its extension
+fn snapshot_format(path: &Path) -> InputFormat {
+    match path.extension() {
+        #[cfg(feature = "serde")]
+        Some(extension) if extension == "json" => InputFormat::Json,
+        _ => InputFormat::Log,
+    }
+}
+
+/// Read a collection of warnings from `path`
+fn load(
+    path: &Path,
+    input_format: InputFormat,
+    args: &Cli,
+    options: &ParseOptions,
+) -> Result<WarningCollection> {
+    let warnings = match input_format {
+        InputFormat::Log => parse_log(path, args, options)?,
+        InputFormat::CppcheckXml => {
+            WarningCollection::from_cppcheck_xml(&read_input(path, args.decompress)?, options)
+                .with_context(|| format!("could not parse cppcheck XML in `{}`", path.display()))?
+        }
+        #[cfg(feature = "serde")]
+        InputFormat::Json => {
+            let json: serde_json::Value = serde_json::from_str(&read_input(path, args.decompress)?)
+                .with_context(|| format!("could not parse JSON in `{}`", path.display()))?;
+            // Snapshots from `--save-baseline-counts` have nothing to recount
+            if json.get("warnings").is_none() {
+                let counts: warnsum::Counts = serde_json::from_value(json)
+                    .with_context(|| format!("could not parse JSON in `{}`", path.display()))?;
+                return Ok(WarningCollection::from_counts(counts));
+            }
+            let mut warnings: WarningCollection = serde_json::from_value(json)
+                .with_context(|| format!("could not parse JSON in `{}`", path.display()))?;
+            if !args.trust_maps {
+                warnings.recount();
+            }
+            warnings
+        }
+    };
+    Ok(warnings)
+}
+
+/// Show whether `warnings` are within each `--max-warnings` budget, and any
+/// `--fail-on` warnings found, on stdout if `to_stdout` or else stderr.
+/// Returns whether they all passed
+fn check_budgets(args: &Cli, warnings: &WarningCollection, to_stdout: bool) -> bool {
+    let mut within_budget = true;
+    for budget in &args.max_warnings {
+        let check = warnings.check_budget(budget);
+        if to_stdout {
+            println!("{check}");
+        } else {
+            eprintln!("{check}");
+        }
+        within_budget &= check.passed();
+    }
+
+    for (name, count) in warnings.find_names(&args.fail_on) {
+        let message = format!("FAIL: banned warning {name} found {count} times");
+        if to_stdout {
+            println!("{message}");
+        } else {
+            eprintln!("{message}");
+        }
+        within_budget = false;
+    }
+    within_budget
+}
+
+/// Exit code with `--exit-codes`: 0 if there are no warnings, 2 if there
+/// are but they're within budget, 3 if they're over budget or getting
+/// worse, and 4 if nothing was parsed from inputs that weren't empty.
+/// Errors always exit with 1
+fn exit_code(warnings_present: bool, within_budget: bool, nothing_parsed: bool) -> i32 {
+    match (warnings_present, within_budget) {
+        _ if nothing_parsed => 4,
+        (_, false) => 3,
+        (true, true) => 2,
+        (false, true) => 0,
+    }
+}
+
+/// Files under the `--scan` directories with names matching `--pattern`, in
+/// order of their paths
+fn scan_logs(args: &Cli) -> Result<Vec<PathBuf>> {
+    let mut logs = Vec::new();
+    for dir in &args.scan {
+        for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) if !args.strict => {
+                    eprintln!("warning: skipping part of `{}`: {error}", dir.display());
+                    continue;
+                }
+                Err(error) => {
+                    return Err(error)
+                        .with_context(|| format!("could not scan `{}`", dir.display()))
+                }
+            };
+            let matches = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| args.pattern.matches(name));
+            if entry.file_type().is_file() && matches {
+                logs.push(entry.into_path());
+            }
+        }
+    }
+    if logs.is_empty() && args.paths.is_empty() {
+        anyhow::bail!("no files matching `{}` found by `--scan`", args.pattern);
+    }
+    Ok(logs)
+}
+
+/// Read the snapshots matching the glob `pattern`, labelled by their file
+/// stems
+fn load_snapshots(
+    pattern: &str,
+    args: &Cli,
+    options: &ParseOptions,
+) -> Result<Vec<(String, WarningCollection)>> {
+    let paths = glob::glob(pattern).with_context(|| format!("invalid glob pattern `{pattern}`"))?;
+    let mut snapshots = Vec::new();
+    for path in paths {
+        let path = path?;
+        let label = path.file_stem().unwrap_or_default().to_string_lossy();
+        let snapshot = load(&path, snapshot_format(&path), args, options)?;
+        snapshots.push((label.to_string(), snapshot));
+    }
+    Ok(snapshots)
+}
+
+/// The summary of `warnings` in `format`
+fn render(
+    format: Format,
+    warnings: &WarningCollection,
+    options: &ReportOptions,
+    delimiter: u8,
+    meta: Option<&Metadata>,
+) -> Result<String> {
+    Ok(match format {
+        Format::Text => match meta {
+            Some(meta) => format!("{meta}\n{}\n", warnings.report(options)),
+            None => format!("{}\n", warnings.report(options)),
+        },
+        Format::Csv | Format::Tsv => warnings.to_delimited(delimiter),
+        Format::Kv => warnings.to_key_values(),
+        Format::Prometheus => warnings.to_prometheus(),
+        #[cfg(feature = "serde")]
+        Format::Json => format!(
+            "{}\n",
+            serde_json::to_string_pretty(&JsonReport::new(warnings, meta))?
+        ),
+    })
+}
+
+/// Check the config files and patterns in `args`, for `check-config`
+fn check_config(args: &Cli) -> Result<()> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("could not read file `{}`", path.display()))
+    };
+
+    // The patterns have already been compiled by parsing the arguments
+    for index in 1..=args.custom_regex.len() {
+        println!("ok: --custom-regex #{index}");
+    }
+    if let Some(path) = &args.alias {
+        let content = read(path)?;
+        content
+            .parse::<Aliases>()
+            .map(|_| ())
+            .and_then(|_| check_mapping(&content))
+            .with_context(|| format!("invalid aliases in `{}`", path.display()))?;
+        println!("ok: --alias `{}`", path.display());
+    }
+    if let Some(path) = &args.weights {
+        let content = read(path)?;
+        content
+            .parse::<Weights>()
+            .map(|_| ())
+            .and_then(|_| check_mapping(&content))
+            .with_context(|| format!("invalid weights in `{}`", path.display()))?;
+        println!("ok: --weights `{}`", path.display());
+    }
+    if args.custom_regex.is_empty() && args.alias.is_none() && args.weights.is_none() {
+        println!("nothing to check, give `--alias`, `--weights`, or `--custom-regex`");
+    }
+    Ok(())
+}
+
+/// What [`show`] printed, for the budget checks that follow it
+#[derive(Default)]
+struct Shown {
+    /// Whether the budget checks can go on stdout after it
+    budgets_to_stdout: bool,
+    /// Whether there are more warnings than in `--baseline`
+    worse: bool,
+}
+
+/// Show `warnings` in the mode chosen by `args`: the report, a diff
+/// against `--baseline`, or one of the other views
+fn show(
+    args: &Cli,
+    format: Format,
+    warnings: &mut WarningCollection,
+    scanned: &[PathBuf],
+    options: &ParseOptions,
+) -> Result<Shown> {
+    if args.tui {
+        browse(warnings)?;
+        return Ok(Shown::default());
+    }
+
+    if let Some(name) = &args.explain {
+        match warnings.explain(name) {
+            Some(block) => println!("{block}"),
+            None => anyhow::bail!("no warnings called `{name}`"),
+        }
+        return Ok(Shown::default());
+    }
+
+    if let Some(file) = &args.annotate {
+        let path = args.root.join(file);
+        match std::fs::read_to_string(&path) {
+            Ok(source) => print!("{}", warnings.annotate(file, &source)),
+            Err(error) => eprintln!("note: skipping `{}`: {error}", path.display()),
+        }
+        return Ok(Shown::default());
+    }
+
+    if args.crosstab {
+        let crosstab = Crosstab::new(warnings, args.top_n, args.crosstab_keywords);
+        match format {
+            Format::Text => print!("{crosstab}"),
+            Format::Csv | Format::Tsv => {
+                print!("{}", crosstab.to_delimited(format.delimiter(args)))
+            }
+            Format::Kv | Format::Prometheus => {
+                anyhow::bail!("`--crosstab` can only be written as text, CSV, TSV, or JSON")
+            }
+            #[cfg(feature = "serde")]
+            Format::Json => println!("{}", serde_json::to_string_pretty(&crosstab)?),
+        }
+        return Ok(Shown::default());
+    }
+
+    if args.keyword_length_histogram {
+        print!("{}", KeywordLengths::new(warnings));
+        return Ok(Shown::default());
+    }
+
+    if let Some(path) = &args.all_files {
+        let manifest = read_input(path, false)?;
+        let clean = warnings.clean_files(&manifest, options);
+        if args.clean_count {
+            println!("{}", clean.len());
+        } else {
+            for file in clean {
+                println!("{}", file.display());
+            }
+        }
+        return Ok(Shown::default());
+    }
+
+    if let Some(path) = &args.baseline {
+        let baseline = load(path, snapshot_format(path), args, options)?;
+        let diff = if args.ignore_path_base {
+            warnings.diff_ignoring_path_base(&baseline)
+        } else {
+            warnings.diff(&baseline)
+        };
+        let diff = if args.only_new_names {
+            diff.only_new_names(&baseline)
+        } else {
+            diff
+        };
+        if args.changed_files {
+            for (file, change) in diff.changed_files() {
+                if args.with_delta {
+                    println!("{}\t{change:+}", file.display());
+                } else {
+                    println!("{}", file.display());
+                }
+            }
+        } else {
+            match args.diff_format {
+                DiffFormat::Text if args.diff_detailed => {
+                    println!("{}", warnings.diff_detailed(&baseline))
+                }
+                DiffFormat::Text => {
+                    let display = diff.display(args.diff_style.into());
+                    if args.relative {
+                        println!("{}", display.relative_to(&baseline));
+                    } else {
+                        println!("{display}");
+                    }
+                }
+                #[cfg(feature = "serde")]
+                DiffFormat::Json => {
+                    let json = JsonDiff {
+                        diff: &diff,
+                        total: diff.total(),
+                    };
+                    println!("{}", serde_json::to_string_pretty(&json)?)
+                }
+            }
+        }
+        // The budgets are of the current warnings, whatever they're compared to
+        return Ok(Shown {
+            budgets_to_stdout: args.diff_format == DiffFormat::Text && !args.changed_files,
+            worse: diff.total() > 0,
+        });
+    }
+
+    if let Some(pattern) = &args.trend {
+        let mut trend = Trend::new();
+        for (label, snapshot) in load_snapshots(pattern, args, options)? {
+            trend.push(&label, &snapshot);
+        }
+        trend.push("current", warnings);
+
+        match format {
+            Format::Text => print!("{trend}"),
+            Format::Csv | Format::Tsv => print!("{}", trend.to_delimited(format.delimiter(args))),
+            Format::Kv | Format::Prometheus => {
+                anyhow::bail!("`--trend` can only be written as text, CSV, TSV, or JSON")
+            }
+            #[cfg(feature = "serde")]
+            Format::Json => println!("{}", serde_json::to_string_pretty(&trend)?),
+        }
+        return Ok(Shown::default());
+    }
+
+    let averages = match &args.avg_baseline {
+        Some(pattern) => {
+            let snapshots = load_snapshots(pattern, args, options)?;
+            if snapshots.is_empty() {
+                anyhow::bail!("no snapshots match `{pattern}`");
+            }
+            let snapshots = snapshots
+                .into_iter()
+                .map(|(_, snapshot)| snapshot)
+                .collect::<Vec<_>>();
+            Some(Averages::new(&snapshots))
+        }
+        None => None,
+    };
+
+    let relative_to = args
+        .strip_common_prefix
+        .then(|| warnings.strip_common_prefix());
+
+    let report_options = ReportOptions {
+        top_n: args.top_n,
+        sections: args.show.clone(),
+        order: args.order.clone(),
+        names_sort: args.names_sort.into(),
+        dirs_sort: args.dirs_sort.into(),
+        tiebreak: args.tiebreak.into(),
+        thousands_sep: args.thousands_sep,
+        ranked: args.ranked,
+        line_template: args.line_template.clone(),
+        collapse_repeats: args.collapse_repeats,
+        stats: args.stats,
+        hotspots: args.hotspots,
+        density: args.density,
+        tree: args.tree,
+        themes: args.themes,
+        signatures: args.dedup_across_files,
+        linker: args.include_linker,
+        weights: args
+            .weights
+            .as_deref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("could not read file `{}`", path.display()))?
+                    .parse::<Weights>()
+                    .with_context(|| format!("invalid weights in `{}`", path.display()))
+            })
+            .transpose()?,
+        distribution: args.distribution.clone(),
+        only: args.only,
+        with_total: args.with_total,
+        oneline: args.oneline,
+        relative_to,
+        max_width: match args.max_width {
+            Some(0) => None,
+            Some(max_width) => Some(max_width),
+            None => terminal_width(),
+        },
+        averages,
+        no_headers: args.no_headers,
+    };
+
+    let inputs = [args.paths.as_slice(), scanned].concat();
+    let meta = args.metadata.then(|| Metadata::new(&inputs));
+    let render_start = args.profile.then(Instant::now);
+    match &args.output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create directory `{}`", dir.display()))?;
+            let report_options = ReportOptions {
+                max_width: report_options
+                    .max_width
+                    .filter(|_| args.max_width.is_some()),
+                ..report_options
+            };
+            for &format in &args.format {
+                let path = dir.join(format.filename());
+                std::fs::write(
+                    &path,
+                    render(
+                        format,
+                        warnings,
+                        &report_options,
+                        format.delimiter(args),
+                        meta.as_ref(),
+                    )?,
+                )
+                .with_context(|| format!("could not write file `{}`", path.display()))?;
+            }
+        }
+        None => print!(
+            "{}",
+            render(
+                format,
+                warnings,
+                &report_options,
+                format.delimiter(args),
+                meta.as_ref()
+            )?
+        ),
+    }
+    if let (Some(profile), Some(start)) = (warnings.profile(), render_start) {
+        let profile = Profile {
+            render: start.elapsed(),
+            ..profile
+        };
+        eprint!("{profile}");
+    }
+
+    // Output from `--only` and `--oneline` is meant for piping elsewhere
+    Ok(Shown {
+        budgets_to_stdout: (format == Format::Text && args.only.is_none() && !args.oneline)
+            || args.output_dir.is_some(),
+        worse: false,
+    })
+}
 
 fn main() -> Result<()> {
-    let args = Cli::parse();
+    let args = Cli::try_parse().unwrap_or_else(|error| {
+        // Clap exits with 2 for usage errors, which `--exit-codes` uses for
+        // warnings within budget
+        if error.use_stderr() && std::env::args_os().any(|arg| arg == "--exit-codes") {
+            let _ = error.print();
+            std::process::exit(1);
+        }
+        error.exit()
+    });
+
+    if let Some(Command::CheckConfig) = args.command {
+        return check_config(&args);
+    }
+
+    if args.format.len() > 1 && args.output_dir.is_none() {
+        anyhow::bail!("several formats can only be given with `--output-dir`");
+    }
+    let format = args.format[0];
+
+    let options = ParseOptions {
+        keyword_len: args.keyword_len,
+        keyword_source: args.keyword_source.into(),
+        ignored_keywords: args.ignore.clone(),
+        ignored_names: args.ignore_warning.clone(),
+        excluded_ranges: args.exclude_range.clone(),
+        aliases: args
+            .alias
+            .as_deref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("could not read file `{}`", path.display()))?
+                    .parse::<Aliases>()
+                    .with_context(|| format!("invalid aliases in `{}`", path.display()))
+            })
+            .transpose()?
+            .unwrap_or_default(),
+        custom_parsers: args
+            .custom_regex
+            .iter()
+            .map(|parser| parser.clone().with_flag_prefix(&args.custom_flag_prefix))
+            .collect(),
+        fold_keyword_case: args.fold_keyword_case,
+        canonicalize_existing: args.canonicalize_existing,
+        keyword_include_numeric: args.keyword_include_numeric,
+        unique_keywords_per_warning: args.unique_keywords_per_warning,
+        keep_raw: args.explain.is_some(),
+        keep_messages: args.dedup_across_files
+            || args.show.contains(&Category::Signatures)
+            || args.only == Some(Category::Signatures),
+        keyword_underlined_only: args.keyword_underlined_only,
+        tab_width: args.tab_width,
+        include_pragmas: args.include_pragmas,
+        include_linker: args.include_linker,
+        include_notes_keywords: args.include_notes_keywords,
+        unwrap_lines: args.unwrap,
+        message_name_words: args.message_name_words,
+        collapse_templates: args.collapse_templates,
+        max_files: args.max_files,
+        max_warnings: args.max_warnings_parsed,
+        sample_every: args.sample,
+        profile: args.profile,
+    };
+
+    let scanned = scan_logs(&args)?;
+
+    if args.dry_run {
+        for path in args.paths.iter().chain(&scanned) {
+            print!(
+                "{}",
+                DryRun::new(&read_input(path, args.decompress)?, &options)
+            );
+        }
+        return Ok(());
+    }
+
+    let mut collections = Vec::new();
+    for path in &args.paths {
+        collections.push(load(path, args.input_format, &args, &options)?);
+    }
+    for path in &scanned {
+        match load(path, args.input_format, &args, &options) {
+            Ok(collection) => collections.push(collection),
+            Err(error) if !args.strict => {
+                eprintln!("warning: skipping `{}`: {error:#}", path.display())
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    let Some(mut warnings) = collections.into_iter().reduce(|mut all, collection| {
+        all.merge(collection);
+        all
+    }) else {
+        anyhow::bail!("none of the files found by `--scan` could be read");
+    };
+
+    if warnings.is_truncated() && !args.quiet {
+        eprintln!("warning: stopped parsing at the `--max-files` or `--max-warnings-parsed` limit, results are truncated");
+    }
+
+    let nothing_parsed = args.warn_if_empty
+        && warnings.total() == 0
+        && args
+            .paths
+            .iter()
+            .chain(&scanned)
+            .any(|path| !is_empty_file(path));
+    if nothing_parsed {
+        eprintln!("*** warnsum: no warnings found, but the input isn't empty ***");
+        eprintln!("The log may be in a format warnsum doesn't recognise. Try `--dry-run` to see which parsers match, or `--custom-regex` to add one");
+    }
+
+    // The saved counts are of everything parsed, but the budgets are only
+    // of what's left after `--suppress-baseline`
+    if let Some(path) = &args.save_baseline_counts {
+        save_counts(path, &warnings)?;
+    }
 
-    let content = std::fs::read_to_string(&args.path)
-        .with_context(|| format!("could not read file `{}`", args.path.display()))?;
+    if let Some(path) = &args.suppress_baseline {
+        warnings.suppress_baseline(&load(path, snapshot_format(path), &args, &options)?)?;
+    }
 
-    let warnings = WarningCollection::new(&content, args.keyword_len, &args.ignore);
+    let shown = show(&args, format, &mut warnings, &scanned, &options)?;
+    let within_budget = check_budgets(&args, &warnings, shown.budgets_to_stdout);
 
-    println!("{warnings:.width$}", width = &args.top_n);
+    if args.exit_codes {
+        std::process::exit(exit_code(
+            warnings.total() > 0,
+            within_budget && !shown.worse,
+            nothing_parsed,
+        ));
+    }
+    if !within_budget || nothing_parsed {
+        std::process::exit(1);
+    }
 
     Ok(())
 }
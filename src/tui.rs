@@ -0,0 +1,192 @@
+// warnsum: summarise compiler warnings
+// Copyright (C) 2023 Peter Hill
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Interactive terminal browser for a [`WarningCollection`]
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, HashSet};
+use warnsum::WarningCollection;
+
+const TITLES: [&str; 4] = ["Warnings", "Files", "Directories", "Keywords"];
+const NAMES: usize = 0;
+
+/// Browse `warnings` until the user quits
+pub fn run(warnings: &WarningCollection) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(warnings).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+struct App<'a> {
+    all: &'a WarningCollection,
+    /// Name the other panes are filtered to, if any
+    filter: Option<String>,
+    /// Items and their counts in each pane, most common first
+    panes: [Vec<(String, i32)>; 4],
+    states: [ListState; 4],
+    focus: usize,
+}
+
+impl<'a> App<'a> {
+    fn new(all: &'a WarningCollection) -> Self {
+        let mut app = App {
+            all,
+            filter: None,
+            panes: Default::default(),
+            states: Default::default(),
+            focus: NAMES,
+        };
+        app.refresh();
+        app
+    }
+
+    /// Recompute the panes from the warnings matching the filter. The names
+    /// pane always shows every name, so the filter can be changed
+    fn refresh(&mut self) {
+        let view = match &self.filter {
+            Some(name) => {
+                let mut view = self.all.clone();
+                view.retain_names(&HashSet::from([name.clone()]));
+                view
+            }
+            None => self.all.clone(),
+        };
+        self.panes = [
+            sorted_items(self.all.names(), |name| name.clone()),
+            sorted_items(view.files(), |file| file.display().to_string()),
+            sorted_items(view.directories(), |dir| dir.display().to_string()),
+            sorted_items(view.keywords(), |keyword| keyword.clone()),
+        ];
+        for (pane, state) in self.panes.iter().zip(&mut self.states) {
+            state.select((!pane.is_empty()).then_some(0));
+        }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Esc if self.filter.is_none() => return Ok(()),
+                KeyCode::Esc => {
+                    self.filter = None;
+                    self.refresh();
+                }
+                KeyCode::Left | KeyCode::BackTab => {
+                    self.focus = (self.focus + TITLES.len() - 1) % TITLES.len()
+                }
+                KeyCode::Right | KeyCode::Tab => self.focus = (self.focus + 1) % TITLES.len(),
+                KeyCode::Up => self.states[self.focus].select_previous(),
+                KeyCode::Down => self.states[self.focus].select_next(),
+                KeyCode::Enter if self.focus == NAMES => self.toggle_filter(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Filter the other panes to the selected name, or stop filtering if
+    /// it's already the filter
+    fn toggle_filter(&mut self) {
+        let Some(index) = self.states[NAMES].selected() else {
+            return;
+        };
+        let Some((name, _)) = self.panes[NAMES].get(index) else {
+            return;
+        };
+        self.filter = match &self.filter {
+            Some(filter) if filter == name => None,
+            _ => Some(name.clone()),
+        };
+        self.refresh();
+        self.states[NAMES].select(Some(index));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let columns = Layout::horizontal([Constraint::Ratio(1, 4); 4]).split(main);
+
+        for (index, title) in TITLES.into_iter().enumerate() {
+            let title = match (&self.filter, index) {
+                (Some(filter), index) if index != NAMES => format!("{title} ({filter})"),
+                _ => title.to_string(),
+            };
+            let border = if index == self.focus {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().add_modifier(Modifier::DIM)
+            };
+            let width = self.panes[index]
+                .iter()
+                .map(|(_, count)| count.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let items = self.panes[index]
+                .iter()
+                .map(|(item, count)| {
+                    let line = format!("{count:>width$}  {item}");
+                    match &self.filter {
+                        Some(filter) if index == NAMES && filter == item => {
+                            Line::styled(line, Style::default().add_modifier(Modifier::UNDERLINED))
+                        }
+                        _ => Line::from(line),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border)
+                        .title(title),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, columns[index], &mut self.states[index]);
+        }
+
+        frame.render_widget(
+            Line::from("←/→ pane  ↑/↓ move  Enter filter by warning  Esc clear filter  q quit"),
+            help,
+        );
+    }
+}
+
+/// Items in `counts` as strings, most common first, ties broken by item
+fn sorted_items<T, F>(counts: &HashMap<T, i32>, to_string: F) -> Vec<(String, i32)>
+where
+    T: Ord,
+    F: Fn(&T) -> String,
+{
+    let mut items = counts.iter().collect::<Vec<_>>();
+    items.sort_by(|lhs, rhs| rhs.1.cmp(lhs.1).then_with(|| lhs.0.cmp(rhs.0)));
+    items
+        .into_iter()
+        .map(|(item, &count)| (to_string(item), count))
+        .collect()
+}
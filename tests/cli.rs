@@ -48,3 +48,883 @@ fn find_content_in_file() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn only_files() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--only").arg("files");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2  /path/to/file2.c"))
+        .stdout(predicate::str::contains("1  /path/to/file1.c"))
+        .stdout(predicate::str::contains(":").not())
+        .stdout(predicate::str::contains("Total").not());
+
+    // Budget checks don't get mixed into output meant for piping
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--only")
+        .arg("files")
+        .arg("--max-warnings=1");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("FAIL").not())
+        .stderr(predicate::str::contains("FAIL"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--oneline")
+        .arg("--max-warnings=5");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("OK").not())
+        .stderr(predicate::str::contains("OK"));
+
+    Ok(())
+}
+
+#[test]
+fn no_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--no-headers");
+    cmd.assert().success().stdout(
+        "\
+2  horrible-stuff
+1  bad-thing
+3  Total
+2  /path/to/file2.c
+1  /path/to/file1.c
+2  Total
+3  /path/to
+1  Total
+
+",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn max_warnings() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--max-warnings").arg("5");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("OK: 3/5"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--max-warnings")
+        .arg("5")
+        .arg("--max-warnings")
+        .arg("horrible-stuff=0");
+    cmd.assert().failure().stdout(predicate::str::contains(
+        "FAIL: horrible-stuff 2/0 (over budget by 2)",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn max_warnings_with_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = assert_fs::NamedTempFile::new("baseline.txt")?;
+    baseline.write_str("/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--max-warnings=0");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("+1  horrible-stuff"))
+        .stdout(predicate::str::contains("FAIL: 2/0 (over budget by 2)"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--fail-on")
+        .arg("bad-thing");
+    cmd.assert().code(1).stdout(predicate::str::contains(
+        "FAIL: banned warning bad-thing found 1 times",
+    ));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(baseline.path())
+        .arg("--baseline")
+        .arg(file.path())
+        .arg("--exit-codes")
+        .arg("--max-warnings=0");
+    cmd.assert().code(3);
+
+    Ok(())
+}
+
+#[test]
+fn custom_regex_missing_group() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("warnsum")?;
+
+    cmd.arg("--custom-regex")
+        .arg(r"(?P<file>\S+): (?P<message>.*)")
+        .arg("test/file/doesnt/exist");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "missing required named group `name`",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn distinct_exit_codes() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    let clean = assert_fs::NamedTempFile::new("clean.txt")?;
+    clean.write_str("[100%] Built target everything\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(clean.path()).arg("--exit-codes");
+    cmd.assert().code(0);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--exit-codes")
+        .arg("--max-warnings=5");
+    cmd.assert().code(2);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--exit-codes")
+        .arg("--max-warnings=1");
+    cmd.assert().code(3);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--exit-codes")
+        .arg("--max-warnings=1")
+        .arg("--crosstab");
+    cmd.assert().code(3);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("missing.txt").arg("--exit-codes");
+    cmd.assert().code(1);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--exit-codes")
+        .arg("--max-warnings=abc");
+    cmd.assert().code(1);
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--max-warnings=abc");
+    cmd.assert().code(2);
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    let output = cmd.arg(file.path()).arg("--format").arg("json").output()?;
+    assert!(output.status.success());
+
+    let json_text = String::from_utf8(output.stdout.clone())?;
+    assert!(json_text.contains(r#""stats": {"#));
+
+    let json = assert_fs::NamedTempFile::new("sample.json")?;
+    json.write_binary(&output.stdout)?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(json.path()).arg("--input-format").arg("json");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2  horrible-stuff"));
+
+    Ok(())
+}
+
+#[test]
+fn changed_files_since_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = assert_fs::NamedTempFile::new("baseline.txt")?;
+    baseline.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file3.c:12:3: warning: doing some bad thing [-Wbad-thing]
+",
+    )?;
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--changed-files");
+    cmd.assert()
+        .success()
+        .stdout("/path/to/file2.c\n/path/to/file3.c\n");
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--changed-files")
+        .arg("--with-delta");
+    cmd.assert()
+        .success()
+        .stdout("/path/to/file2.c\t+1\n/path/to/file3.c\t-1\n");
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--with-delta");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn diff_against_baseline_as_json() -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = assert_fs::NamedTempFile::new("baseline.txt")?;
+    baseline.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:720:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--baseline")
+        .arg(baseline.path())
+        .arg("--diff-format")
+        .arg("json");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""horrible-stuff": 2"#))
+        .stdout(predicate::str::contains(r#""/path/to/file2.c": 2"#))
+        .stdout(predicate::str::contains(r#""total": 2"#))
+        .stdout(predicate::str::contains("bad-thing").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--baseline").arg(baseline.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+2  horrible-stuff"))
+        .stdout(predicate::str::contains("Net: +2"));
+
+    Ok(())
+}
+
+#[test]
+fn baseline_of_counts_only() -> Result<(), Box<dyn std::error::Error>> {
+    let old = assert_fs::NamedTempFile::new("old.txt")?;
+    old.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:340:27: warning: don't like this [-Wdont-like-this]
+",
+    )?;
+    let new = assert_fs::NamedTempFile::new("new.txt")?;
+    new.write_str("/path/to/file3.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+    let baseline = assert_fs::NamedTempFile::new("baseline.json")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(old.path())
+        .arg("--save-baseline-counts")
+        .arg(baseline.path());
+    cmd.assert().success();
+    baseline.assert(predicate::str::contains("bad-thing"));
+    baseline.assert(predicate::str::contains("path").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(new.path()).arg("--baseline").arg(baseline.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("-1  dont-like-this"))
+        .stdout(predicate::str::contains("file3.c").not());
+
+    // The saved counts include the warnings that `--suppress-baseline` hides
+    let saved = assert_fs::NamedTempFile::new("saved.json")?;
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(old.path())
+        .arg("--suppress-baseline")
+        .arg(old.path())
+        .arg("--save-baseline-counts")
+        .arg(saved.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("dont-like-this").not());
+    saved.assert(predicate::str::contains("dont-like-this"));
+
+    Ok(())
+}
+
+#[test]
+fn write_formats_to_output_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    let dir = assert_fs::TempDir::new()?;
+    let reports = dir.child("reports");
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--output-dir")
+        .arg(reports.path())
+        .arg("--format")
+        .arg("text,csv");
+    cmd.assert().success().stdout("");
+
+    reports
+        .child("summary.txt")
+        .assert(predicate::str::contains("1  horrible-stuff"));
+    reports
+        .child("summary.csv")
+        .assert(predicate::str::contains("names,horrible-stuff,1"));
+    reports
+        .child("summary.json")
+        .assert(predicate::path::missing());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--format").arg("text,csv");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn metadata_header() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    let log = "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n";
+    file.write_str(log)?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--metadata");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("Generated: "))
+        .stdout(predicate::str::contains("\nHost: "))
+        .stdout(predicate::str::contains(format!(
+            "sample.txt ({} bytes)\n\nDetected: gcc",
+            log.len()
+        )));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Host: ").not());
+
+    Ok(())
+}
+
+#[test]
+fn limit_warnings_parsed() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:340:27: warning: doing some bad thing [-Wbad-thing]
+/path/to/file3.c:697:16: warning: doing some bad thing [-Wbad-thing]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--max-warnings-parsed", "2"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2  bad-thing"))
+        .stderr(predicate::str::contains("results are truncated"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--max-files", "1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1  bad-thing"))
+        .stderr(predicate::str::contains("results are truncated"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--max-files", "3"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("3  bad-thing"))
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn sample_warnings() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:340:27: warning: doing some bad thing [-Wbad-thing]
+/path/to/file3.c:697:16: warning: doing some bad thing [-Wbad-thing]
+/path/to/file4.c:697:16: warning: doing some bad thing [-Wbad-thing]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--sample", "1/2"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Estimated from 1 in 2 warnings"))
+        .stdout(predicate::str::contains("4  bad-thing"))
+        .stdout(predicate::str::contains("2  /path/to/file1.c"))
+        .stdout(predicate::str::contains("file2.c").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--sample", "2"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("expected `1/N`"));
+
+    Ok(())
+}
+
+#[test]
+fn tail_of_log() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |   horrible(stuff);
+      |   ^~~~~~~~
+/path/to/file2.c:340:27: warning: don't like this [-Wdont-like-this]
+  340 |   zing(zimb);
+      |   ^~~~
+/path/to/file3.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+  697 |   horrible(stuff);
+      |   ^~~~~~~~
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--tail", "4"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1  dont-like-this"))
+        .stdout(predicate::str::contains("1  horrible-stuff"))
+        .stdout(predicate::str::contains("bad-thing").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--head", "1"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1  bad-thing"))
+        .stdout(predicate::str::contains("1  horrible\n"))
+        .stdout(predicate::str::contains("dont-like-this").not());
+
+    Ok(())
+}
+
+#[test]
+fn stream_large_log() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    let mut log = String::new();
+    for index in 0..1000 {
+        log.push_str(&format!(
+            "/path/to/dir{}/file{index}.c:235:36: warning: doing some bad thing [-Wbad-thing]
+  235 |     if (horrible) *foo = zing->zimb;
+      |                                ^~~~~
+/path/to/dir{}/file{index}.c:340:27: warning: don't like this [-Wdont-like-this]
+",
+            index % 7,
+            index % 3
+        ));
+    }
+    file.write_str(&log)?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    let expected = cmd.arg(file.path()).args(["-n", "0"]).output()?;
+    assert!(expected.status.success());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["-n", "0", "--stream"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1000  bad-thing"))
+        .stdout(String::from_utf8(expected.stdout)?);
+
+    Ok(())
+}
+
+#[test]
+fn delimited_output() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str("/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).args(["--format", "tsv"]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "category\tkey\tcount\nnames\tbad-thing\t1\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .args(["--format", "csv", "--delimiter", ";"]);
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "category;key;count\nnames;bad-thing;1\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .args(["--format", "csv", "--delimiter", "ab"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "expected a single ASCII character",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn annotate_source() -> Result<(), Box<dyn std::error::Error>> {
+    let root = assert_fs::TempDir::new()?;
+    root.child("src/main.c").write_str(
+        "int main() {
+  int x;
+  return 0;
+}
+",
+    )?;
+    let log = root.child("build.log");
+    log.write_str("src/main.c:2:7: warning: unused variable 'x' [-Wunused-variable]\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(log.path())
+        .args(["--annotate", "src/main.c", "--root"])
+        .arg(root.path());
+    cmd.assert().success().stdout(
+        "int main() {
+  int x;
+>>> 2:7: warning unused-variable
+  return 0;
+}
+",
+    );
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(log.path())
+        .args(["--annotate", "src/missing.c", "--root"])
+        .arg(root.path());
+    cmd.assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("note: skipping"));
+
+    Ok(())
+}
+
+#[test]
+fn warn_if_nothing_parsed() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str("Compiling...\nBuild finished, no problems here\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--warn-if-empty");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("no warnings found"))
+        .stderr(predicate::str::contains("--dry-run"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--warn-if-empty")
+        .arg("--exit-codes");
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("no warnings found"));
+
+    let snapshot = assert_fs::NamedTempFile::new("day1.log")?;
+    snapshot.write_str("/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--warn-if-empty")
+        .arg("--exit-codes")
+        .arg("--trend")
+        .arg(snapshot.path());
+    cmd.assert()
+        .code(4)
+        .stderr(predicate::str::contains("no warnings found"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--warn-if-empty")
+        .arg("--crosstab");
+    cmd.assert().code(1);
+
+    let empty = assert_fs::NamedTempFile::new("empty.txt")?;
+    empty.touch()?;
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(empty.path()).arg("--warn-if-empty");
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn fail_on_banned_warnings() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--fail-on").arg("return-type");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("FAIL").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--fail-on")
+        .arg("return-type")
+        .arg("horrible-*");
+    cmd.assert().failure().stdout(predicate::str::contains(
+        "FAIL: banned warning horrible-stuff found 2 times",
+    ));
+
+    // Every mode checks for banned warnings, not just the report
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path())
+        .arg("--fail-on")
+        .arg("horrible-*")
+        .arg("--crosstab");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "FAIL: banned warning horrible-stuff found 2 times",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn trend_over_snapshots() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("day1.log").write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    dir.child("day2.log")
+        .write_str("/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(dir.child("day2.log").path())
+        .arg("--trend")
+        .arg(dir.child("*.log").path())
+        .arg("--format")
+        .arg("csv");
+    cmd.assert().success().stdout(
+        "snapshot,warnings,names,files,directories,keywords
+day1,2,2,2,1,0
+day2,1,1,1,1,0
+current,1,1,1,1,0
+",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn decompress_input() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(
+        b"/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+/path/to/file2.c:715:18: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    let file = assert_fs::NamedTempFile::new("sample.txt.gz")?;
+    file.write_binary(&encoder.finish()?)?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--decompress");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2  horrible-stuff"));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "http"))]
+#[test]
+fn url_without_http_feature() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("https://ci.example/build/123/log.txt");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("without the `http` feature"));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "tui"))]
+#[test]
+fn tui_without_tui_feature() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str("/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg(file.path()).arg("--tui");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("without the `tui` feature"));
+
+    Ok(())
+}
+
+#[test]
+fn check_valid_config() -> Result<(), Box<dyn std::error::Error>> {
+    let aliases = assert_fs::NamedTempFile::new("aliases.txt")?;
+    aliases.write_str("# gcc 4 spelling\nmaybe-uninitialized = uninitialized\n")?;
+    let weights = assert_fs::NamedTempFile::new("weights.txt")?;
+    weights.write_str("uninitialized=10\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("check-config")
+        .arg("--alias")
+        .arg(aliases.path())
+        .arg("--weights")
+        .arg(weights.path())
+        .args(["--custom-regex", r"^(?P<file>\S+):(?P<name>\w+)"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ok: --alias"))
+        .stdout(predicate::str::contains("ok: --weights"))
+        .stdout(predicate::str::contains("ok: --custom-regex #1"));
+
+    Ok(())
+}
+
+#[test]
+fn check_malformed_config() -> Result<(), Box<dyn std::error::Error>> {
+    let aliases = assert_fs::NamedTempFile::new("aliases.txt")?;
+    aliases.write_str("uninitialised=uninitialized\n\nuninitialised=maybe-uninitialized\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("check-config").arg("--alias").arg(aliases.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "line 3: `uninitialised` was already given on line 1",
+    ));
+
+    let weights = assert_fs::NamedTempFile::new("weights.txt")?;
+    weights.write_str("uninitialized=lots\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("check-config").arg("--weights").arg(weights.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid weights"))
+        .stderr(predicate::str::contains("line 1"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.args(["check-config", "--custom-regex", r"^(?P<file>\S+):("]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid regex"));
+
+    Ok(())
+}
+
+#[test]
+fn scan_directory_tree() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = assert_fs::TempDir::new()?;
+    dir.child("build/lib/compile.log").write_str(
+        "/path/to/file1.c:235:36: warning: doing some bad thing [-Wbad-thing]
+/path/to/file2.c:697:16: warning: just horrible stuff [-Whorrible-stuff]
+",
+    )?;
+    dir.child("build/app/nested/compile.log")
+        .write_str("/path/to/file3.c:715:18: warning: just horrible stuff [-Whorrible-stuff]\n")?;
+    dir.child("build/app/notes.txt")
+        .write_str("/path/to/file4.c:1:1: warning: not a log [-Wnot-a-log]\n")?;
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("--scan").arg(dir.child("build").path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2  horrible-stuff"))
+        .stdout(predicate::str::contains("1  bad-thing"))
+        .stdout(predicate::str::contains("not-a-log").not());
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("--scan")
+        .arg(dir.child("build").path())
+        .args(["--pattern", "*.txt"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1  not-a-log"));
+
+    let mut cmd = Command::cargo_bin("warnsum")?;
+    cmd.arg("--scan")
+        .arg(dir.child("build").path())
+        .args(["--pattern", "*.json"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no files matching `*.json`"));
+
+    Ok(())
+}